@@ -1,268 +1,329 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, str::FromStr};
+
+use crate::parser;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Old,
+    Value(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expression {
+    Add(Operand, Operand),
+    Mult(Operand, Operand),
+}
 
 #[derive(Debug, Clone)]
 struct Monkey {
-    items_worry: VecDeque<u64>,
-    operation: fn(u64) -> u64,
+    items_worry: VecDeque<u128>,
+    operation: Expression,
     test_divisibility: u64,
     if_true_monkey: usize,
     if_false_monkey: usize,
 }
 
-pub fn part1() -> usize {
-    let mut monkeys: Vec<Monkey> = vec![
-        Monkey {
-            items_worry: VecDeque::from(vec![93, 54, 69, 66, 71]),
-            operation: |old| old * 3,
-            test_divisibility: 7,
-            if_true_monkey: 7,
-            if_false_monkey: 1,
-        },
-        Monkey {
-            items_worry: VecDeque::from(vec![89, 51, 80, 66]),
-            operation: |old| old * 17,
-            test_divisibility: 19,
-            if_true_monkey: 5,
-            if_false_monkey: 7,
-        },
-        Monkey {
-            items_worry: VecDeque::from(vec![90, 92, 63, 91, 96, 63, 64]),
-            operation: |old| old + 1,
-            test_divisibility: 13,
-            if_true_monkey: 4,
-            if_false_monkey: 3,
-        },
-        Monkey {
-            items_worry: VecDeque::from(vec![65, 77]),
-            operation: |old| old + 2,
-            test_divisibility: 3,
-            if_true_monkey: 4,
-            if_false_monkey: 6,
-        },
-        Monkey {
-            items_worry: VecDeque::from(vec![76, 68, 94]),
-            operation: |old| old * old,
-            test_divisibility: 2,
-            if_true_monkey: 0,
-            if_false_monkey: 6,
-        },
-        Monkey {
-            items_worry: VecDeque::from(vec![86, 65, 66, 97, 73, 83]),
-            operation: |old| old + 8,
-            test_divisibility: 11,
-            if_true_monkey: 2,
-            if_false_monkey: 3,
-        },
-        Monkey {
-            items_worry: VecDeque::from(vec![78]),
-            operation: |old| old + 6,
-            test_divisibility: 17,
-            if_true_monkey: 0,
-            if_false_monkey: 1,
-        },
-        Monkey {
-            items_worry: VecDeque::from(vec![89, 57, 59, 61, 87, 55, 55, 88]),
-            operation: |old| old + 7,
-            test_divisibility: 5,
-            if_true_monkey: 2,
-            if_false_monkey: 5,
-        },
-    ];
-
-    monkey_business(&mut monkeys)
+#[derive(Debug, Clone)]
+struct Simulation {
+    monkeys: Vec<Monkey>,
+    inspections: Vec<usize>,
+    relief: u64,
+    // Only without relief can worries be kept modulo the divisors, as the division by the
+    // relief would not give the same remainders afterwards
+    worry_modulus: Option<u128>,
 }
 
-pub fn part2() -> usize {
-    let mut monkeys: Vec<Monkey> = vec![
-        Monkey {
-            items_worry: VecDeque::from(vec![93, 54, 69, 66, 71]),
-            operation: |old| old * 3,
-            test_divisibility: 7,
-            if_true_monkey: 7,
-            if_false_monkey: 1,
-        },
-        Monkey {
-            items_worry: VecDeque::from(vec![89, 51, 80, 66]),
-            operation: |old| old * 17,
-            test_divisibility: 19,
-            if_true_monkey: 5,
-            if_false_monkey: 7,
-        },
-        Monkey {
-            items_worry: VecDeque::from(vec![90, 92, 63, 91, 96, 63, 64]),
-            operation: |old| old + 1,
-            test_divisibility: 13,
-            if_true_monkey: 4,
-            if_false_monkey: 3,
-        },
-        Monkey {
-            items_worry: VecDeque::from(vec![65, 77]),
-            operation: |old| old + 2,
-            test_divisibility: 3,
-            if_true_monkey: 4,
-            if_false_monkey: 6,
-        },
-        Monkey {
-            items_worry: VecDeque::from(vec![76, 68, 94]),
-            operation: |old| old * old,
-            test_divisibility: 2,
-            if_true_monkey: 0,
-            if_false_monkey: 6,
-        },
-        Monkey {
-            items_worry: VecDeque::from(vec![86, 65, 66, 97, 73, 83]),
-            operation: |old| old + 8,
-            test_divisibility: 11,
-            if_true_monkey: 2,
-            if_false_monkey: 3,
-        },
-        Monkey {
-            items_worry: VecDeque::from(vec![78]),
-            operation: |old| old + 6,
-            test_divisibility: 17,
-            if_true_monkey: 0,
-            if_false_monkey: 1,
-        },
-        Monkey {
-            items_worry: VecDeque::from(vec![89, 57, 59, 61, 87, 55, 55, 88]),
-            operation: |old| old + 7,
-            test_divisibility: 5,
-            if_true_monkey: 2,
-            if_false_monkey: 5,
-        },
-    ];
-
-    monkey_business_ten_thousand(&mut monkeys)
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ParseMonkeyError(String);
+
+// The monkey whose operation made a worry too big to keep exactly
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WorryOverflow(usize);
+
+impl Operand {
+    fn value(&self, old: u128) -> u128 {
+        match self {
+            Operand::Old => old,
+            Operand::Value(value) => *value as u128,
+        }
+    }
 }
 
-fn monkey_business(monkeys: &mut Vec<Monkey>) -> usize {
-    let mut inspections = vec![0; monkeys.len()];
+impl FromStr for Operand {
+    type Err = ParseMonkeyError;
 
-    for _ in 0..20 {
-        for mi in 0..monkeys.len() {
-            while let Some(item_worry) = monkeys[mi].items_worry.pop_front() {
-                inspections[mi] += 1;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "old" => Ok(Operand::Old),
+            _ => s
+                .parse()
+                .map(Operand::Value)
+                .map_err(|_| ParseMonkeyError(format!("invalid operand '{s}'"))),
+        }
+    }
+}
 
-                let mut new_worry = (monkeys[mi].operation)(item_worry);
-                new_worry /= 3;
-                let throw_index = if new_worry % monkeys[mi].test_divisibility == 0 {
-                    monkeys[mi].if_true_monkey
-                } else {
-                    monkeys[mi].if_false_monkey
-                };
-                monkeys[throw_index as usize].items_worry.push_back(new_worry);
-            }
+impl Expression {
+    fn evaluate(&self, old: u128) -> Option<u128> {
+        match self {
+            Expression::Add(a, b) => a.value(old).checked_add(b.value(old)),
+            Expression::Mult(a, b) => a.value(old).checked_mul(b.value(old)),
         }
     }
+}
+
+impl FromStr for Expression {
+    type Err = ParseMonkeyError;
 
-    inspections.sort();
-    inspections.iter().rev().take(2).product()
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens: Vec<&str> = s.split_whitespace().collect();
+        match tokens[..] {
+            [a, "+", b] => Ok(Expression::Add(a.parse()?, b.parse()?)),
+            [a, "*", b] => Ok(Expression::Mult(a.parse()?, b.parse()?)),
+            _ => Err(ParseMonkeyError(format!("invalid expression '{s}'"))),
+        }
+    }
 }
 
-fn monkey_business_ten_thousand(monkeys: &mut Vec<Monkey>) -> usize {
-    let mut inspections = vec![0; monkeys.len()];
+impl FromStr for Monkey {
+    type Err = ParseMonkeyError;
 
-    let worry_overflow: u64 = monkeys
-        .iter()
-        .map(|monkey| monkey.test_divisibility)
-        .product();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let field = |prefix: &str| {
+            s.lines()
+                .map(str::trim)
+                .find_map(|line| line.strip_prefix(prefix))
+                .ok_or_else(|| ParseMonkeyError(format!("missing '{prefix}'")))
+        };
+        let number = |prefix: &str| {
+            let raw = field(prefix)?;
+            raw.trim()
+                .parse()
+                .map_err(|_| ParseMonkeyError(format!("invalid number '{raw}'")))
+        };
 
-    for _ in 0..10000 {
-        for mi in 0..monkeys.len() {
-            while let Some(item_worry) = monkeys[mi].items_worry.pop_front() {
-                inspections[mi] += 1;
+        let items_worry = field("Starting items:")?
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                item.parse()
+                    .map_err(|_| ParseMonkeyError(format!("invalid item '{item}'")))
+            })
+            .collect::<Result<_, _>>()?;
 
-                let mut new_worry = (monkeys[mi].operation)(item_worry);
-                new_worry %= worry_overflow;
-                let throw_index = if new_worry % monkeys[mi].test_divisibility == 0 {
-                    monkeys[mi].if_true_monkey
+        Ok(Monkey {
+            items_worry,
+            operation: field("Operation: new =")?.parse()?,
+            test_divisibility: number("Test: divisible by")?,
+            if_true_monkey: number("If true: throw to monkey")? as usize,
+            if_false_monkey: number("If false: throw to monkey")? as usize,
+        })
+    }
+}
+
+impl Simulation {
+    fn new(monkeys: Vec<Monkey>, relief: u64) -> Self {
+        let worry_modulus = (relief == 1).then(|| {
+            monkeys
+                .iter()
+                .map(|monkey| monkey.test_divisibility as u128)
+                .product()
+        });
+
+        Simulation {
+            inspections: vec![0; monkeys.len()],
+            monkeys,
+            relief,
+            worry_modulus,
+        }
+    }
+
+    fn run(&mut self, rounds: usize) -> Result<(), WorryOverflow> {
+        for _ in 0..rounds {
+            self.round()?;
+        }
+        Ok(())
+    }
+
+    fn round(&mut self) -> Result<(), WorryOverflow> {
+        for mi in 0..self.monkeys.len() {
+            while let Some(item_worry) = self.monkeys[mi].items_worry.pop_front() {
+                self.inspections[mi] += 1;
+
+                let monkey = &self.monkeys[mi];
+                let mut new_worry = monkey
+                    .operation
+                    .evaluate(item_worry)
+                    .ok_or(WorryOverflow(mi))?
+                    / self.relief as u128;
+                if let Some(modulus) = self.worry_modulus {
+                    new_worry %= modulus;
+                }
+                let throw_index = if new_worry.is_multiple_of(monkey.test_divisibility as u128) {
+                    monkey.if_true_monkey
                 } else {
-                    monkeys[mi].if_false_monkey
+                    monkey.if_false_monkey
                 };
-                monkeys[throw_index as usize].items_worry.push_back(new_worry);
+                self.monkeys[throw_index].items_worry.push_back(new_worry);
             }
         }
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    fn inspections(&self) -> &[usize] {
+        &self.inspections
     }
 
-    inspections.sort();
-    inspections.iter().rev().take(2).product()
+    #[allow(dead_code)]
+    fn items(&self) -> Vec<Vec<u128>> {
+        self.monkeys
+            .iter()
+            .map(|monkey| monkey.items_worry.iter().copied().collect())
+            .collect()
+    }
+
+    fn monkey_business(&self) -> usize {
+        let mut inspections = self.inspections.clone();
+        inspections.sort();
+        inspections.iter().rev().take(2).product()
+    }
+}
+
+pub fn part1() -> usize {
+    let lines: Vec<String> = parser::read("data/day11.txt").unwrap();
+    let monkeys = parse(&lines).unwrap();
+    monkey_business(monkeys, 20, 3).unwrap()
+}
+
+pub fn part2() -> usize {
+    let lines: Vec<String> = parser::read("data/day11.txt").unwrap();
+    let monkeys = parse(&lines).unwrap();
+    monkey_business(monkeys, 10000, 1).unwrap()
+}
+
+fn monkey_business(
+    monkeys: Vec<Monkey>,
+    rounds: usize,
+    relief: u64,
+) -> Result<usize, WorryOverflow> {
+    let mut simulation = Simulation::new(monkeys, relief);
+    simulation.run(rounds)?;
+    Ok(simulation.monkey_business())
+}
+
+fn parse(lines: &[String]) -> Result<Vec<Monkey>, ParseMonkeyError> {
+    lines
+        .split(|line| line.is_empty())
+        .filter(|notes| !notes.is_empty())
+        .map(|notes| notes.join("\n").parse())
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::VecDeque;
-
     use super::*;
 
+    fn sample_monkeys() -> Vec<Monkey> {
+        let input = vec![
+            "Monkey 0:",
+            "  Starting items: 79, 98",
+            "  Operation: new = old * 19",
+            "  Test: divisible by 23",
+            "    If true: throw to monkey 2",
+            "    If false: throw to monkey 3",
+            "",
+            "Monkey 1:",
+            "  Starting items: 54, 65, 75, 74",
+            "  Operation: new = old + 6",
+            "  Test: divisible by 19",
+            "    If true: throw to monkey 2",
+            "    If false: throw to monkey 0",
+            "",
+            "Monkey 2:",
+            "  Starting items: 79, 60, 97",
+            "  Operation: new = old * old",
+            "  Test: divisible by 13",
+            "    If true: throw to monkey 1",
+            "    If false: throw to monkey 3",
+            "",
+            "Monkey 3:",
+            "  Starting items: 74",
+            "  Operation: new = old + 3",
+            "  Test: divisible by 17",
+            "    If true: throw to monkey 0",
+            "    If false: throw to monkey 1",
+        ];
+        let lines: Vec<String> = input.iter().map(|s| s.to_string()).collect();
+        parse(&lines).unwrap()
+    }
+
+    #[test]
+    fn parsing() {
+        let monkeys = sample_monkeys();
+
+        assert_eq!(monkeys.len(), 4);
+        assert_eq!(monkeys[1].items_worry, VecDeque::from(vec![54, 65, 75, 74]));
+        assert_eq!(
+            monkeys[2].operation,
+            Expression::Mult(Operand::Old, Operand::Old)
+        );
+        assert_eq!(monkeys[2].operation.evaluate(7), Some(49));
+        assert_eq!(monkeys[3].test_divisibility, 17);
+        assert_eq!(monkeys[3].if_true_monkey, 0);
+        assert_eq!(monkeys[3].if_false_monkey, 1);
+    }
+
+    #[test]
+    fn parsing_errors() {
+        assert!("old - 3".parse::<Expression>().is_err());
+        assert!("old * x".parse::<Expression>().is_err());
+        assert!("Monkey 0:\n  Starting items: 1".parse::<Monkey>().is_err());
+    }
+
+    #[test]
+    fn items_after_round() {
+        let mut simulation = Simulation::new(sample_monkeys(), 3);
+        simulation.round().unwrap();
+
+        assert_eq!(
+            simulation.items(),
+            vec![
+                vec![20, 23, 27, 26],
+                vec![2080, 25, 167, 207, 401, 1046],
+                vec![],
+                vec![]
+            ]
+        );
+    }
+
+    #[test]
+    fn other_relief() {
+        // Worries are kept exactly, as dividing after reducing them would change them
+        let mut simulation = Simulation::new(sample_monkeys(), 2);
+        simulation.run(12).unwrap();
+        assert_eq!(simulation.inspections(), &[58, 58, 7, 61]);
+
+        let mut simulation = Simulation::new(sample_monkeys(), 2);
+        assert_eq!(simulation.run(1000), Err(WorryOverflow(2)));
+    }
+
     #[test]
     fn sample_input_part_1() {
-        let mut monkeys: Vec<Monkey> = vec![
-            Monkey {
-                items_worry: VecDeque::from(vec![79, 98]),
-                operation: |old| old * 19,
-                test_divisibility: 23,
-                if_true_monkey: 2,
-                if_false_monkey: 3,
-            },
-            Monkey {
-                items_worry: VecDeque::from(vec![54, 65, 75, 74]),
-                operation: |old| old + 6,
-                test_divisibility: 19,
-                if_true_monkey: 2,
-                if_false_monkey: 0,
-            },
-            Monkey {
-                items_worry: VecDeque::from(vec![79, 60, 97]),
-                operation: |old| old * old,
-                test_divisibility: 13,
-                if_true_monkey: 1,
-                if_false_monkey: 3,
-            },
-            Monkey {
-                items_worry: VecDeque::from(vec![74]),
-                operation: |old| old + 3,
-                test_divisibility: 17,
-                if_true_monkey: 0,
-                if_false_monkey: 1,
-            },
-        ];
+        let mut simulation = Simulation::new(sample_monkeys(), 3);
+        simulation.run(20).unwrap();
 
-        assert_eq!(monkey_business(&mut monkeys), 10605);
+        assert_eq!(simulation.inspections(), &[101, 95, 7, 105]);
+        assert_eq!(simulation.monkey_business(), 10605);
     }
 
     #[test]
     fn sample_input_part_2() {
-        let mut monkeys: Vec<Monkey> = vec![
-            Monkey {
-                items_worry: VecDeque::from(vec![79, 98]),
-                operation: |old| old * 19,
-                test_divisibility: 23,
-                if_true_monkey: 2,
-                if_false_monkey: 3,
-            },
-            Monkey {
-                items_worry: VecDeque::from(vec![54, 65, 75, 74]),
-                operation: |old| old + 6,
-                test_divisibility: 19,
-                if_true_monkey: 2,
-                if_false_monkey: 0,
-            },
-            Monkey {
-                items_worry: VecDeque::from(vec![79, 60, 97]),
-                operation: |old| old * old,
-                test_divisibility: 13,
-                if_true_monkey: 1,
-                if_false_monkey: 3,
-            },
-            Monkey {
-                items_worry: VecDeque::from(vec![74]),
-                operation: |old| old + 3,
-                test_divisibility: 17,
-                if_true_monkey: 0,
-                if_false_monkey: 1,
-            },
-        ];
+        let mut simulation = Simulation::new(sample_monkeys(), 1);
+        simulation.run(20).unwrap();
+        assert_eq!(simulation.inspections(), &[99, 97, 8, 103]);
 
-        assert_eq!(monkey_business_ten_thousand(&mut monkeys), 2713310158);
+        assert_eq!(monkey_business(sample_monkeys(), 10000, 1), Ok(2713310158));
     }
 }
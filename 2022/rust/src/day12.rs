@@ -1,25 +1,48 @@
-use std::{
-    collections::{HashSet, VecDeque},
-    rc::Rc,
-};
+use std::collections::VecDeque;
 
 use crate::parser;
 
 type Position = (usize, usize);
 
-struct Problem {
+// How much the elevation may change in a single step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ClimbRule {
+    max_up: usize,
+    max_down: Option<usize>,
+}
+
+impl Default for ClimbRule {
+    fn default() -> Self {
+        ClimbRule {
+            max_up: 1,
+            max_down: None,
+        }
+    }
+}
+
+impl ClimbRule {
+    fn allows(&self, from: usize, to: usize) -> bool {
+        if to >= from {
+            to - from <= self.max_up
+        } else {
+            self.max_down.is_none_or(|max_down| from - to <= max_down)
+        }
+    }
+}
+
+struct HeightMap {
     start: Position,
     end: Position,
     map: Vec<Vec<usize>>,
 }
 
-impl Problem {
-    fn parse(lines: &Vec<String>) -> Self {
+impl HeightMap {
+    fn parse(lines: &[String]) -> Self {
         let mut start = (0, 0);
         let mut end = (0, 0);
 
-        for y in 0..lines.len() {
-            for (x, c) in lines[y].chars().enumerate() {
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
                 if c == 'S' {
                     start = (x, y);
                 } else if c == 'E' {
@@ -43,81 +66,133 @@ impl Problem {
 
         Self { start, end, map }
     }
-}
 
-pub fn part1() -> usize {
-    let lines: Vec<String> = parser::read("data/day12.txt").unwrap();
-    let problem = Problem::parse(&lines);
-    fewest_steps(problem)
-}
+    fn width(&self) -> usize {
+        self.map.first().map_or(0, |row| row.len())
+    }
 
-pub fn part2() -> usize {
-    let lines: Vec<String> = parser::read("data/day12.txt").unwrap();
-    let problem = Problem::parse(&lines);
-    fewest_steps_from_base(problem)
-}
+    fn height(&self) -> usize {
+        self.map.len()
+    }
 
-fn fewest_steps(problem: Problem) -> usize {
-    let width = problem.map[0].len();
-    let height = problem.map.len();
-    let mut considered: HashSet<Rc<Position>> = HashSet::new();
+    fn elevation(&self, (x, y): Position) -> usize {
+        self.map[y][x]
+    }
+
+    fn neighbours(&self, (x, y): Position) -> impl Iterator<Item = Position> + '_ {
+        [
+            x.checked_sub(1).map(|x| (x, y)),
+            Some((x + 1, y)).filter(|&(x, _)| x < self.width()),
+            y.checked_sub(1).map(|y| (x, y)),
+            Some((x, y + 1)).filter(|&(_, y)| y < self.height()),
+        ]
+        .into_iter()
+        .flatten()
+    }
 
-    let mut next_positions = VecDeque::new();
-    let start = Rc::new(problem.start);
-    next_positions.push_back((start.clone(), 0));
-    considered.insert(Rc::clone(&start));
+    // Single BFS walking backwards from the end, so every cell learns its distance at once
+    fn routes_to_end(&self, rule: ClimbRule) -> Routes {
+        let mut distances = vec![vec![None; self.width()]; self.height()];
+        let mut next_steps = vec![vec![None; self.width()]; self.height()];
+        let mut queue = VecDeque::from([self.end]);
+        distances[self.end.1][self.end.0] = Some(0);
 
-    loop {
-        if next_positions.len() == 0 {
-            return 1000000; // TODO fix this mega hardcoded break condition
+        while let Some(position) = queue.pop_front() {
+            let steps = distances[position.1][position.0].unwrap();
+            for (x, y) in self.neighbours(position) {
+                if distances[y][x].is_none()
+                    && rule.allows(self.elevation((x, y)), self.elevation(position))
+                {
+                    distances[y][x] = Some(steps + 1);
+                    next_steps[y][x] = Some(position);
+                    queue.push_back((x, y));
+                }
+            }
         }
 
-        let (position_rc, steps) = next_positions.pop_front().unwrap();
-        let x = position_rc.0;
-        let y = position_rc.1;
-        if (x, y) == problem.end {
-            return steps;
+        Routes {
+            distances,
+            next_steps,
         }
+    }
 
-        let candidates = vec![
-            (x.checked_sub(1).unwrap_or(0), y),
-            ((x + 1).min(width - 1), y),
-            (x, y.checked_sub(1).unwrap_or(0)),
-            (x, (y + 1).min(height - 1)),
-        ];
-        for (cx, cy) in candidates {
-            let candidate_position = Rc::new((cx, cy));
+    #[allow(dead_code)]
+    fn render_path(&self, path: &[Position]) -> String {
+        let mut cells = vec![vec!['.'; self.width()]; self.height()];
+        for step in path.windows(2) {
+            let ((x, y), (nx, ny)) = (step[0], step[1]);
+            cells[y][x] = match (nx as isize - x as isize, ny as isize - y as isize) {
+                (1, 0) => '>',
+                (-1, 0) => '<',
+                (0, 1) => 'v',
+                _ => '^',
+            };
+        }
+        if let Some(&(x, y)) = path.last() {
+            cells[y][x] = 'E';
+        }
 
-            let valid_step = (0..=problem.map[y][x] + 1).contains(&problem.map[cy][cx]);
-            let not_seen = !considered.contains(&candidate_position);
+        cells
+            .iter()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
 
-            if valid_step && not_seen {
-                next_positions.push_back((candidate_position.clone(), steps + 1));
-                considered.insert(Rc::clone(&candidate_position));
-            }
+struct Routes {
+    distances: Vec<Vec<Option<usize>>>,
+    next_steps: Vec<Vec<Option<Position>>>,
+}
+
+impl Routes {
+    fn distance(&self, (x, y): Position) -> Option<usize> {
+        self.distances[y][x]
+    }
+
+    #[allow(dead_code)]
+    fn path(&self, from: Position) -> Option<Vec<Position>> {
+        self.distance(from)?;
+
+        let mut path = vec![from];
+        let mut position = from;
+        while let Some(next) = self.next_steps[position.1][position.0] {
+            path.push(next);
+            position = next;
         }
+        Some(path)
     }
 }
 
-fn fewest_steps_from_base(problem: Problem) -> usize {
-    find_starts(&problem)
-        .iter()
-        .map(|(x, y)| {
-            fewest_steps(Problem {
-                start: (*x, *y),
-                end: problem.end,
-                map: problem.map.clone(),
-            })
-        })
+pub fn part1() -> usize {
+    let lines: Vec<String> = parser::read("data/day12.txt").unwrap();
+    let height_map = HeightMap::parse(&lines);
+    fewest_steps(&height_map, ClimbRule::default()).unwrap()
+}
+
+pub fn part2() -> usize {
+    let lines: Vec<String> = parser::read("data/day12.txt").unwrap();
+    let height_map = HeightMap::parse(&lines);
+    fewest_steps_from_base(&height_map, ClimbRule::default()).unwrap()
+}
+
+fn fewest_steps(height_map: &HeightMap, rule: ClimbRule) -> Option<usize> {
+    height_map.routes_to_end(rule).distance(height_map.start)
+}
+
+fn fewest_steps_from_base(height_map: &HeightMap, rule: ClimbRule) -> Option<usize> {
+    let routes = height_map.routes_to_end(rule);
+    find_starts(height_map)
+        .into_iter()
+        .filter_map(|start| routes.distance(start))
         .min()
-        .unwrap()
 }
 
-fn find_starts(problem: &Problem) -> Vec<Position> {
+fn find_starts(height_map: &HeightMap) -> Vec<Position> {
     let mut starts = vec![];
 
-    for y in 0..problem.map.len() {
-        for (x, &c) in problem.map[y].iter().enumerate() {
+    for (y, row) in height_map.map.iter().enumerate() {
+        for (x, &c) in row.iter().enumerate() {
             if c == 'a' as usize {
                 starts.push((x, y));
             }
@@ -131,41 +206,94 @@ fn find_starts(problem: &Problem) -> Vec<Position> {
 mod tests {
     use super::*;
 
+    fn sample_map() -> HeightMap {
+        let lines = vec![
+            String::from("Sabqponm"),
+            String::from("abcryxxl"),
+            String::from("accszExk"),
+            String::from("acctuvwj"),
+            String::from("abdefghi"),
+        ];
+        HeightMap::parse(&lines)
+    }
+
     #[test]
     fn parse() {
         let lines = vec![String::from("Sacde"), String::from("fghiE")];
-        let problem = Problem::parse(&lines);
-        assert_eq!(problem.start, (0, 0));
-        assert_eq!(problem.end, (4, 1));
+        let height_map = HeightMap::parse(&lines);
+        assert_eq!(height_map.start, (0, 0));
+        assert_eq!(height_map.end, (4, 1));
         assert_eq!(
-            problem.map,
+            height_map.map,
             vec![vec![97, 97, 99, 100, 101], vec![102, 103, 104, 105, 122]]
         );
     }
 
+    #[test]
+    fn unreachable_end() {
+        let lines = vec![String::from("SacE")];
+        let height_map = HeightMap::parse(&lines);
+        let routes = height_map.routes_to_end(ClimbRule::default());
+
+        assert_eq!(fewest_steps(&height_map, ClimbRule::default()), None);
+        assert_eq!(routes.path((0, 0)), None);
+        assert_eq!(routes.distance((2, 0)), None);
+    }
+
+    #[test]
+    fn path_rendering() {
+        let height_map = sample_map();
+        let path = height_map
+            .routes_to_end(ClimbRule::default())
+            .path(height_map.start)
+            .unwrap();
+
+        assert_eq!(path.len(), 32);
+        assert_eq!(path.first(), Some(&height_map.start));
+        assert_eq!(path.last(), Some(&height_map.end));
+        assert!(path.windows(2).all(|step| {
+            let ((x, y), (nx, ny)) = (step[0], step[1]);
+            x.abs_diff(nx) + y.abs_diff(ny) == 1
+        }));
+
+        let rendered = height_map.render_path(&path);
+        assert_eq!(rendered.lines().count(), 5);
+        assert_eq!(rendered.matches(['>', '<', '^', 'v']).count(), 31);
+        assert_eq!(rendered.lines().nth(2).unwrap().chars().nth(5), Some('E'));
+    }
+
+    #[test]
+    fn custom_climb_rule() {
+        let height_map = sample_map();
+        let rule = ClimbRule {
+            max_up: 25,
+            max_down: Some(25),
+        };
+        assert_eq!(fewest_steps(&height_map, rule), Some(7));
+
+        let ridge = HeightMap {
+            start: (0, 0),
+            end: (3, 0),
+            map: vec![vec![0, 1, 0, 1]],
+        };
+        let rule = ClimbRule {
+            max_up: 1,
+            max_down: Some(0),
+        };
+        assert_eq!(fewest_steps(&ridge, ClimbRule::default()), Some(3));
+        assert_eq!(fewest_steps(&ridge, rule), None);
+    }
+
     #[test]
     fn sample_input_part_1() {
-        let lines = vec![
-            String::from("Sabqponm"),
-            String::from("abcryxxl"),
-            String::from("accszExk"),
-            String::from("acctuvwj"),
-            String::from("abdefghi"),
-        ];
-        let problem = Problem::parse(&lines);
-        assert_eq!(fewest_steps(problem), 31);
+        assert_eq!(fewest_steps(&sample_map(), ClimbRule::default()), Some(31));
     }
 
     #[test]
     fn sample_input_part_2() {
-        let lines = vec![
-            String::from("Sabqponm"),
-            String::from("abcryxxl"),
-            String::from("accszExk"),
-            String::from("acctuvwj"),
-            String::from("abdefghi"),
-        ];
-        let problem = Problem::parse(&lines);
-        assert_eq!(fewest_steps_from_base(problem), 29);
+        assert_eq!(
+            fewest_steps_from_base(&sample_map(), ClimbRule::default()),
+            Some(29)
+        );
     }
 }
@@ -1,46 +1,135 @@
-use std::{cmp::Ordering, fmt::Display};
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
 
 use itertools::Itertools;
+use serde_json::Value;
 
 use crate::parser;
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 enum Packet {
     List(Vec<Packet>),
     Value(i32),
 }
 
-impl Packet {
-    fn parse(raw: &str) -> Self {
-        let mut current_list: Vec<Packet> = vec![];
-        let mut token = String::new();
-        let mut stack = vec![];
-
-        for c in raw.chars() {
-            match c {
-                '[' => {
-                    stack.push(current_list);
-                    current_list = vec![];
-                }
-                ']' | ',' => {
-                    if !token.is_empty() {
-                        let value: i32 = token.parse().unwrap();
-                        current_list.push(Packet::Value(value));
-                        token = String::new();
-                    }
-                    if c == ']' {
-                        let mut previous_list = stack.pop().unwrap();
-                        previous_list.push(Packet::List(current_list));
-                        current_list = previous_list;
-                    }
-                }
-                _ => {
-                    token.push(c);
+#[derive(PartialEq, Eq, Debug)]
+#[allow(dead_code)]
+enum ParsePacketError {
+    UnexpectedChar(usize, char),
+    UnexpectedEnd(usize),
+    InvalidNumber(usize),
+    TrailingInput(usize),
+    NotAPacket(String),
+}
+
+struct PacketParser<'a> {
+    raw: &'a str,
+    position: usize,
+}
+
+impl PacketParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.raw[self.position..].chars().next()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParsePacketError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.position += c.len_utf8();
+                Ok(())
+            }
+            Some(c) => Err(ParsePacketError::UnexpectedChar(self.position, c)),
+            None => Err(ParsePacketError::UnexpectedEnd(self.position)),
+        }
+    }
+
+    fn packet(&mut self) -> Result<Packet, ParsePacketError> {
+        match self.peek() {
+            Some('[') => self.list(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.value(),
+            Some(c) => Err(ParsePacketError::UnexpectedChar(self.position, c)),
+            None => Err(ParsePacketError::UnexpectedEnd(self.position)),
+        }
+    }
+
+    fn list(&mut self) -> Result<Packet, ParsePacketError> {
+        self.expect('[')?;
+        let mut values = vec![];
+
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(Packet::List(values));
+        }
+
+        loop {
+            values.push(self.packet()?);
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(']') => {
+                    self.position += 1;
+                    return Ok(Packet::List(values));
                 }
+                Some(c) => return Err(ParsePacketError::UnexpectedChar(self.position, c)),
+                None => return Err(ParsePacketError::UnexpectedEnd(self.position)),
             }
         }
+    }
 
-        current_list.into_iter().next().unwrap()
+    fn value(&mut self) -> Result<Packet, ParsePacketError> {
+        let start = self.position;
+        if self.peek() == Some('-') {
+            self.position += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+
+        self.raw[start..self.position]
+            .parse()
+            .map(Packet::Value)
+            .map_err(|_| ParsePacketError::InvalidNumber(start))
+    }
+}
+
+impl FromStr for Packet {
+    type Err = ParsePacketError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = PacketParser {
+            raw: s,
+            position: 0,
+        };
+        let packet = parser.packet()?;
+
+        match parser.peek() {
+            None => Ok(packet),
+            Some(_) => Err(ParsePacketError::TrailingInput(parser.position)),
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl Packet {
+    fn to_json(&self) -> Value {
+        match self {
+            Packet::List(values) => Value::Array(values.iter().map(Packet::to_json).collect()),
+            Packet::Value(value) => Value::from(*value),
+        }
+    }
+
+    fn from_json(json: &Value) -> Result<Self, ParsePacketError> {
+        match json {
+            Value::Array(values) => values
+                .iter()
+                .map(Packet::from_json)
+                .collect::<Result<_, _>>()
+                .map(Packet::List),
+            Value::Number(number) => number
+                .as_i64()
+                .and_then(|value| i32::try_from(value).ok())
+                .map(Packet::Value)
+                .ok_or_else(|| ParsePacketError::NotAPacket(json.to_string())),
+            _ => Err(ParsePacketError::NotAPacket(json.to_string())),
+        }
     }
 }
 
@@ -60,25 +149,14 @@ impl Ord for Packet {
                     Ordering::Equal => None,
                     result => Some(result),
                 })
-                .unwrap_or_else(|| {
-                    Packet::Value(left_values.len() as i32)
-                        .cmp(&Packet::Value(right_values.len() as i32))
-                }),
+                .unwrap_or_else(|| left_values.len().cmp(&right_values.len())),
             (Packet::List(_), Packet::Value(right_value)) => {
                 self.cmp(&Packet::List(vec![Packet::Value(*right_value)]))
             }
             (Packet::Value(left_value), Packet::List(_)) => {
                 Packet::List(vec![Packet::Value(*left_value)]).cmp(other)
             }
-            (Packet::Value(left_value), Packet::Value(right_value)) => {
-                if left_value < right_value {
-                    Ordering::Less
-                } else if left_value > right_value {
-                    Ordering::Greater
-                } else {
-                    Ordering::Equal
-                }
-            }
+            (Packet::Value(left_value), Packet::Value(right_value)) => left_value.cmp(right_value),
         }
     }
 }
@@ -99,22 +177,14 @@ impl Display for Packet {
 
 pub fn part1() -> usize {
     let lines: Vec<String> = parser::read("data/day13.txt").unwrap();
-    let result: Vec<(usize, Ordering)> = lines
-        .split(|line| line.is_empty())
-        .map(|pair| {
-            let mut pair_iter = pair.iter();
-            let left = pair_iter.next().unwrap();
-            let right = pair_iter.next().unwrap();
-            Packet::parse(left).cmp(&Packet::parse(right))
-        })
-        .enumerate()
-        .collect();
+    let packets = parse(&lines).unwrap();
 
-    result
-        .iter()
-        .filter(|(_, result)| result == &Ordering::Less)
+    packets
+        .chunks(2)
+        .enumerate()
+        .filter(|(_, pair)| pair[0] < pair[1])
         .map(|(i, _)| i + 1)
-        .sum::<usize>() as usize
+        .sum()
 }
 
 pub fn part2() -> usize {
@@ -122,46 +192,51 @@ pub fn part2() -> usize {
     decoder_key(&lines)
 }
 
-fn decoder_key(lines: &Vec<String>) -> usize {
-    let mut packets: Vec<Packet> = lines
+fn parse(lines: &[String]) -> Result<Vec<Packet>, ParsePacketError> {
+    lines
         .iter()
         .filter(|line| !line.is_empty())
-        .map(|line| Packet::parse(line))
-        .collect();
+        .map(|line| line.parse())
+        .collect()
+}
 
-    let first_divider = Packet::parse("[[2]]");
-    let second_divider = Packet::parse("[[6]]");
+// For callers that already keep their packets sorted
+#[allow(dead_code)]
+fn insert_sorted(packets: &mut Vec<Packet>, packet: Packet) -> usize {
+    let index = packets.partition_point(|existing| existing < &packet);
+    packets.insert(index, packet);
+    index
+}
 
-    packets.push(first_divider);
-    packets.push(second_divider);
-    packets.sort();
+fn decoder_key(lines: &[String]) -> usize {
+    let packets = parse(lines).unwrap();
+    let first_divider: Packet = "[[2]]".parse().unwrap();
+    let second_divider: Packet = "[[6]]".parse().unwrap();
 
-    for packet in &packets {
-        println!("{}", packet);
-    }
+    // A divider ends up after every smaller packet, the second one also after the first
+    let smaller_than = |divider: &Packet| packets.iter().filter(|p| *p < divider).count();
+    let first_divider_index = 1 + smaller_than(&first_divider);
+    let second_divider_index = 2 + smaller_than(&second_divider);
 
-    let first_divider_index = 1 + packets
-        .iter()
-        .position(|packet| packet == &Packet::parse("[[2]]"))
-        .unwrap();
-    let second_divider_index = 1 + packets
-        .iter()
-        .position(|packet| packet == &Packet::parse("[[6]]"))
-        .unwrap();
-
-    (first_divider_index * second_divider_index) as usize
+    first_divider_index * second_divider_index
 }
 
 #[cfg(test)]
 mod tests {
     use std::cmp::Ordering;
 
+    use serde_json::json;
+
     use super::*;
 
+    fn packet(raw: &str) -> Packet {
+        raw.parse().unwrap()
+    }
+
     #[test]
     fn parsing() {
         assert_eq!(
-            Packet::parse("[1,2,[8,9],[[]],30,4,5]"),
+            packet("[1,2,[8,9],[[]],30,4,5]"),
             Packet::List(vec![
                 Packet::Value(1),
                 Packet::Value(2),
@@ -171,42 +246,89 @@ mod tests {
                 Packet::Value(4),
                 Packet::Value(5)
             ])
-        )
+        );
+        assert_eq!(
+            packet("[-12,[345]]"),
+            Packet::List(vec![
+                Packet::Value(-12),
+                Packet::List(vec![Packet::Value(345)])
+            ])
+        );
+        assert_eq!(packet("7"), Packet::Value(7));
     }
 
     #[test]
-    fn sample_input_part_1() {
+    fn parsing_errors() {
         assert_eq!(
-            Packet::parse("[1,1,3,1,1]").cmp(&Packet::parse("[1,1,5,1,1]")),
-            Ordering::Less
+            "[1,2".parse::<Packet>(),
+            Err(ParsePacketError::UnexpectedEnd(4))
         );
         assert_eq!(
-            Packet::parse("[[1],[2,3,4]]").cmp(&Packet::parse("[[1],4]")),
-            Ordering::Less
+            "[1;2]".parse::<Packet>(),
+            Err(ParsePacketError::UnexpectedChar(2, ';'))
         );
         assert_eq!(
-            Packet::parse("[9]").cmp(&Packet::parse("[[8,7,6]]")),
-            Ordering::Greater
+            "[1,-]".parse::<Packet>(),
+            Err(ParsePacketError::InvalidNumber(3))
         );
         assert_eq!(
-            Packet::parse("[[4,4],4,4]").cmp(&Packet::parse("[[4,4],4,4,4]")),
+            "[1]]".parse::<Packet>(),
+            Err(ParsePacketError::TrailingInput(3))
+        );
+        assert_eq!(
+            "".parse::<Packet>(),
+            Err(ParsePacketError::UnexpectedEnd(0))
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let original = packet("[1,[2,[3,[4,[5,6,-7]]]],8,9]");
+
+        assert_eq!(original.to_string(), "[1,[2,[3,[4,[5,6,-7]]]],8,9]");
+        assert_eq!(
+            original.to_json(),
+            json!([1, [2, [3, [4, [5, 6, -7]]]], 8, 9])
+        );
+        assert_eq!(Packet::from_json(&original.to_json()), Ok(original));
+        assert_eq!(
+            Packet::from_json(&json!([1, "two"])),
+            Err(ParsePacketError::NotAPacket("\"two\"".to_string()))
+        );
+    }
+
+    #[test]
+    fn sorted_insertion() {
+        let mut packets = vec![packet("[1]"), packet("[[3]]"), packet("[7]")];
+
+        assert_eq!(insert_sorted(&mut packets, packet("[[2]]")), 1);
+        assert_eq!(insert_sorted(&mut packets, packet("[[6]]")), 3);
+        assert_eq!(packets.iter().join(" "), "[1] [[2]] [[3]] [[6]] [7]");
+    }
+
+    #[test]
+    fn sample_input_part_1() {
+        assert_eq!(
+            packet("[1,1,3,1,1]").cmp(&packet("[1,1,5,1,1]")),
             Ordering::Less
         );
         assert_eq!(
-            Packet::parse("[7,7,7,7]").cmp(&Packet::parse("[7,7,7]")),
-            Ordering::Greater
+            packet("[[1],[2,3,4]]").cmp(&packet("[[1],4]")),
+            Ordering::Less
         );
+        assert_eq!(packet("[9]").cmp(&packet("[[8,7,6]]")), Ordering::Greater);
         assert_eq!(
-            Packet::parse("[]").cmp(&Packet::parse("[3]")),
+            packet("[[4,4],4,4]").cmp(&packet("[[4,4],4,4,4]")),
             Ordering::Less
         );
         assert_eq!(
-            Packet::parse("[[[]]]").cmp(&Packet::parse("[[]]")),
+            packet("[7,7,7,7]").cmp(&packet("[7,7,7]")),
             Ordering::Greater
         );
+        assert_eq!(packet("[]").cmp(&packet("[3]")), Ordering::Less);
+        assert_eq!(packet("[[[]]]").cmp(&packet("[[]]")), Ordering::Greater);
         assert_eq!(
-            Packet::parse("[1,[2,[3,[4,[5,6,7]]]],8,9]")
-                .cmp(&Packet::parse("[1,[2,[3,[4,[5,6,0]]]],8,9]")),
+            packet("[1,[2,[3,[4,[5,6,7]]]],8,9]").cmp(&packet("[1,[2,[3,[4,[5,6,0]]]],8,9]")),
             Ordering::Greater
         );
     }
@@ -214,7 +336,7 @@ mod tests {
     #[test]
     fn troubleshooting() {
         assert_eq!(
-            Packet::parse("[1,1,3,1,1]").cmp(&Packet::parse("[1,[2,[3,[4,[5,6,0]]]],8,9]")),
+            packet("[1,1,3,1,1]").cmp(&packet("[1,[2,[3,[4,[5,6,0]]]],8,9]")),
             Ordering::Less
         );
     }
@@ -5,99 +5,239 @@ use crate::parser;
 type Point = (usize, usize);
 type Path = Vec<Point>;
 
-pub fn part1() -> usize {
-    let lines: Vec<String> = parser::read("data/day14.txt").unwrap();
-    let paths: Vec<Path> = lines.iter().map(|line| parse(line)).collect();
-    resting_sands(&paths)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Air,
+    Rock,
+    Sand,
 }
 
-pub fn part2() -> usize {
-    let lines: Vec<String> = parser::read("data/day14.txt").unwrap();
-    let paths: Vec<Path> = lines.iter().map(|line| parse(line)).collect();
-    resting_sands_with_floor(&paths)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bottom {
+    Abyss,
+    Floor(usize),
 }
 
-fn parse(raw: &str) -> Path {
-    raw.split(" -> ")
-        .map(|point_raw| {
-            let mut elements = point_raw.split(",");
-            (
-                elements.next().unwrap().parse().unwrap(),
-                elements.next().unwrap().parse().unwrap(),
-            )
-        })
-        .collect()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Drop {
+    Rested,
+    Fell,
+    Blocked,
 }
 
-fn resting_sands(paths: &Vec<Path>) -> usize {
-    let mut sands = 0;
-    let mut occupied: HashSet<Point> = rock_places(paths);
-    let max_y = *occupied.iter().map(|(_, y)| y).max().unwrap();
+enum Step {
+    Move(Position),
+    Rest,
+    Fall,
+}
 
-    loop {
-        let mut x = 500;
-        let mut y = 0;
-        loop {
-            if y > max_y {
-                return sands;
-            }
+type Position = (i64, usize);
+
+struct Cave {
+    cells: Vec<Cell>,
+    left: i64,
+    width: usize,
+    height: usize,
+    bottom: Bottom,
+    sources: Vec<Position>,
+    source_paths: Vec<Vec<Position>>,
+}
+
+impl Cave {
+    fn new(paths: &[Path], sources: &[Point], bottom: Bottom) -> Self {
+        let rocks = rock_places(paths);
+        let max_y = rocks.iter().map(|&(_, y)| y).max().unwrap_or(0);
+        let sources: Vec<Position> = sources.iter().map(|&(x, y)| (x as i64, y)).collect();
+
+        let height = match bottom {
+            Bottom::Abyss => max_y + 1,
+            Bottom::Floor(depth) => max_y + depth,
+        };
+        let spread = match bottom {
+            Bottom::Abyss => 0,
+            Bottom::Floor(_) => height as i64,
+        };
+        let xs = rocks
+            .iter()
+            .map(|&(x, _)| (x as i64, x as i64))
+            .chain(sources.iter().map(|&(x, _)| (x - spread, x + spread)));
+        let left = xs.clone().map(|(min, _)| min).min().unwrap_or(0);
+        let right = xs.map(|(_, max)| max).max().unwrap_or(0);
+        let width = (right - left + 1) as usize;
 
-            let occupied_down = occupied.contains(&(x, y + 1));
-            let occupied_down_left = occupied.contains(&(x - 1, y + 1));
-            let occupied_down_right = occupied.contains(&(x + 1, y + 1));
+        let mut cave = Cave {
+            cells: vec![Cell::Air; width * height],
+            left,
+            width,
+            height,
+            bottom,
+            source_paths: vec![vec![]; sources.len()],
+            sources,
+        };
+        for (x, y) in rocks {
+            cave.set((x as i64, y), Cell::Rock);
+        }
+        cave
+    }
+
+    fn index(&self, (x, y): Position) -> Option<usize> {
+        let column = x - self.left;
+        if column < 0 || column as usize >= self.width || y >= self.height {
+            None
+        } else {
+            Some(y * self.width + column as usize)
+        }
+    }
 
-            if occupied_down && occupied_down_left && occupied_down_right {
-                occupied.insert((x, y));
-                sands += 1;
-                break;
+    fn cell(&self, position: Position) -> Option<Cell> {
+        self.index(position).map(|i| self.cells[i])
+    }
+
+    fn set(&mut self, position: Position, cell: Cell) {
+        if let Some(i) = self.index(position) {
+            self.cells[i] = cell;
+        }
+    }
+
+    fn step(&self, (x, y): Position) -> Step {
+        if y + 1 == self.height {
+            if let Bottom::Floor(_) = self.bottom {
+                return Step::Rest;
             }
+        }
 
-            if occupied_down && !occupied_down_left {
-                x -= 1;
-            } else if occupied_down && !occupied_down_right {
-                x += 1;
+        for next in [(x, y + 1), (x - 1, y + 1), (x + 1, y + 1)] {
+            match self.cell(next) {
+                Some(Cell::Air) => return Step::Move(next),
+                Some(_) => {}
+                None => return Step::Fall,
             }
-            y += 1;
         }
+        Step::Rest
     }
-}
 
-fn resting_sands_with_floor(paths: &Vec<Path>) -> usize {
-    let mut sands = 0;
-    let mut occupied: HashSet<Point> = rock_places(paths);
-    let max_y = *occupied.iter().map(|(_, y)| y).max().unwrap();
-    let floor_y = max_y + 2;
+    fn drop_grain(&mut self, source: usize) -> Drop {
+        let mut path = std::mem::take(&mut self.source_paths[source]);
+        while path
+            .last()
+            .is_some_and(|&position| self.cell(position) != Some(Cell::Air))
+        {
+            path.pop();
+        }
+        if path.is_empty() {
+            match self.cell(self.sources[source]) {
+                Some(Cell::Air) => path.push(self.sources[source]),
+                Some(_) => return Drop::Blocked,
+                None => return Drop::Fell,
+            }
+        }
 
-    loop {
-        let mut x = 500;
-        let mut y = 0;
-        loop {
-            let floor_is_down = (y + 1) == floor_y;
-            let occupied_down = floor_is_down || occupied.contains(&(x, y + 1));
-            let occupied_down_left = floor_is_down || occupied.contains(&(x - 1, y + 1));
-            let occupied_down_right = floor_is_down || occupied.contains(&(x + 1, y + 1));
+        let result = loop {
+            let position = *path.last().unwrap();
+            match self.step(position) {
+                Step::Move(next) => path.push(next),
+                Step::Fall => break Drop::Fell,
+                Step::Rest => {
+                    self.set(position, Cell::Sand);
+                    path.pop();
+                    break Drop::Rested;
+                }
+            }
+        };
 
-            if occupied_down && occupied_down_left && occupied_down_right {
-                occupied.insert((x, y));
-                sands += 1;
+        self.source_paths[source] = path;
+        result
+    }
 
-                if y == 0 {
-                    return sands;
+    fn fill(&mut self) -> usize {
+        let mut rested = 0;
+        loop {
+            let mut active = false;
+            for source in 0..self.sources.len() {
+                match self.drop_grain(source) {
+                    Drop::Rested => {
+                        rested += 1;
+                        active = true;
+                    }
+                    Drop::Fell => return rested,
+                    Drop::Blocked => {}
                 }
-                break;
             }
-
-            if occupied_down && !occupied_down_left {
-                x -= 1;
-            } else if occupied_down && !occupied_down_right {
-                x += 1;
+            if !active {
+                return rested;
             }
-            y += 1;
         }
     }
+
+    #[allow(dead_code)]
+    fn resting_sands(&self) -> usize {
+        self.cells
+            .iter()
+            .filter(|&&cell| cell == Cell::Sand)
+            .count()
+    }
+
+    #[allow(dead_code)]
+    fn render(&self) -> String {
+        let columns: Vec<i64> = (0..self.width)
+            .filter(|&column| {
+                (0..self.height).any(|y| self.cells[y * self.width + column] != Cell::Air)
+            })
+            .map(|column| column as i64 + self.left)
+            .chain(self.sources.iter().map(|&(x, _)| x))
+            .collect();
+        let min_x = *columns.iter().min().unwrap();
+        let max_x = *columns.iter().max().unwrap();
+
+        let mut rows: Vec<String> = (0..self.height)
+            .map(|y| {
+                (min_x..=max_x)
+                    .map(|x| match self.cell((x, y)) {
+                        Some(Cell::Rock) => '#',
+                        Some(Cell::Sand) => 'o',
+                        _ if self.sources.contains(&(x, y)) => '+',
+                        _ => '.',
+                    })
+                    .collect()
+            })
+            .collect();
+        if let Bottom::Floor(_) = self.bottom {
+            rows.push("#".repeat((max_x - min_x + 1) as usize));
+        }
+        rows.join("\n")
+    }
+}
+
+pub fn part1() -> usize {
+    let lines: Vec<String> = parser::read("data/day14.txt").unwrap();
+    let paths: Vec<Path> = lines.iter().map(|line| parse(line)).collect();
+    resting_sands(&paths, Bottom::Abyss)
+}
+
+pub fn part2() -> usize {
+    let lines: Vec<String> = parser::read("data/day14.txt").unwrap();
+    let paths: Vec<Path> = lines.iter().map(|line| parse(line)).collect();
+    resting_sands(&paths, Bottom::Floor(2))
 }
 
-fn rock_places(paths: &Vec<Path>) -> HashSet<Point> {
+fn parse(raw: &str) -> Path {
+    raw.split(" -> ")
+        .map(|point_raw| {
+            let mut elements = point_raw.split(',');
+            (
+                elements.next().unwrap().parse().unwrap(),
+                elements.next().unwrap().parse().unwrap(),
+            )
+        })
+        .collect()
+}
+
+fn resting_sands(paths: &[Path], bottom: Bottom) -> usize {
+    let mut cave = Cave::new(paths, &[(500, 0)], bottom);
+    cave.fill()
+}
+
+fn rock_places(paths: &[Path]) -> HashSet<Point> {
     let mut rocks: HashSet<Point> = HashSet::new();
 
     for path in paths {
@@ -165,13 +305,45 @@ mod tests {
         assert_eq!(rock_places(&paths), expected_rock);
     }
 
-    // #[test]
-    // fn map() {
-    //     let paths = vec![
-    //         parse("498,4 -> 498,6 -> 496,6"),
-    //         parse("503,4 -> 502,4 -> 502,9 -> 494,9"),
-    //     ];
-    // }
+    #[test]
+    fn map() {
+        let paths = vec![
+            parse("498,4 -> 498,6 -> 496,6"),
+            parse("503,4 -> 502,4 -> 502,9 -> 494,9"),
+        ];
+        let mut cave = Cave::new(&paths, &[(500, 0)], Bottom::Abyss);
+        cave.fill();
+
+        assert_eq!(cave.resting_sands(), 24);
+        assert_eq!(
+            cave.render(),
+            [
+                "......+...",
+                "..........",
+                "......o...",
+                ".....ooo..",
+                "....#ooo##",
+                "...o#ooo#.",
+                "..###ooo#.",
+                "....oooo#.",
+                ".o.ooooo#.",
+                "#########.",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn multiple_sources() {
+        let paths = vec![parse("3,1 -> 4,1")];
+        let mut cave = Cave::new(&paths, &[(1, 0), (5, 0)], Bottom::Floor(2));
+
+        assert_eq!(cave.fill(), 16);
+        assert_eq!(
+            cave.render(),
+            ["..o...o..", ".ooo##oo.", "ooooooooo", "#########"].join("\n")
+        );
+    }
 
     #[test]
     fn sample_input_part_1() {
@@ -179,7 +351,7 @@ mod tests {
             parse("498,4 -> 498,6 -> 496,6"),
             parse("503,4 -> 502,4 -> 502,9 -> 494,9"),
         ];
-        assert_eq!(resting_sands(&paths), 24);
+        assert_eq!(resting_sands(&paths, Bottom::Abyss), 24);
     }
 
     #[test]
@@ -188,6 +360,6 @@ mod tests {
             parse("498,4 -> 498,6 -> 496,6"),
             parse("503,4 -> 502,4 -> 502,9 -> 494,9"),
         ];
-        assert_eq!(resting_sands_with_floor(&paths), 93);
+        assert_eq!(resting_sands(&paths, Bottom::Floor(2)), 93);
     }
 }
@@ -12,12 +12,68 @@ struct Sensor {
     beacon: Point,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Area {
+    min: Point,
+    max: Point,
+}
+
 impl Sensor {
-    fn distance(self: &Self) -> i32 {
-        let x_distance = (self.at.0 - self.beacon.0).abs();
-        let y_distance = (self.at.1 - self.beacon.1).abs();
-        x_distance + y_distance
+    fn distance(&self) -> i32 {
+        manhattan(self.at, self.beacon)
+    }
+
+    fn covers(&self, point: Point) -> bool {
+        manhattan(self.at, point) <= self.distance()
+    }
+
+    fn range_at_y(&self, at_y: i32) -> Option<RangeInclusive<i32>> {
+        let width_in_y = self.distance() - (self.at.1 - at_y).abs();
+        (width_in_y >= 0).then(|| self.at.0 - width_in_y..=self.at.0 + width_in_y)
+    }
+}
+
+impl Area {
+    fn square(size: i32) -> Self {
+        Area {
+            min: (0, 0),
+            max: (size, size),
+        }
+    }
+
+    fn contains(&self, (x, y): Point) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y)
     }
+
+    fn corners(&self) -> [Point; 4] {
+        [
+            self.min,
+            (self.max.0, self.min.1),
+            (self.min.0, self.max.1),
+            self.max,
+        ]
+    }
+
+    #[allow(dead_code)]
+    fn quadrants(&self) -> Vec<Area> {
+        let mid_x = self.min.0 + (self.max.0 - self.min.0) / 2;
+        let mid_y = self.min.1 + (self.max.1 - self.min.1) / 2;
+        let xs = [(self.min.0, mid_x), (mid_x + 1, self.max.0)];
+        let ys = [(self.min.1, mid_y), (mid_y + 1, self.max.1)];
+
+        xs.iter()
+            .cartesian_product(ys.iter())
+            .filter(|((min_x, max_x), (min_y, max_y))| min_x <= max_x && min_y <= max_y)
+            .map(|(&(min_x, max_x), &(min_y, max_y))| Area {
+                min: (min_x, min_y),
+                max: (max_x, max_y),
+            })
+            .collect()
+    }
+}
+
+fn manhattan(a: Point, b: Point) -> i32 {
+    (a.0 - b.0).abs() + (a.1 - b.1).abs()
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -51,77 +107,128 @@ pub fn part1() -> usize {
 
 pub fn part2() -> usize {
     let sensors: Vec<Sensor> = parser::read("data/day15.txt").unwrap();
-    tunning_frequency(&sensors, 4000000)
+    tunning_frequency(&sensors, &Area::square(4000000)).unwrap()
 }
 
-fn no_beacon_positions(sensors: &Vec<Sensor>, at_y: i32) -> usize {
-    unique_ranges_at_y(sensors, at_y)
+fn no_beacon_positions(sensors: &[Sensor], at_y: i32) -> usize {
+    let covered: usize = unique_ranges_at_y(sensors, at_y)
         .into_iter()
         .map(|range| range.count())
-        .sum::<usize>() as usize
-        - 1
+        .sum();
+    let beacons_at_y = sensors
+        .iter()
+        .map(|sensor| sensor.beacon)
+        .filter(|beacon| beacon.1 == at_y)
+        .unique()
+        .count();
+
+    covered - beacons_at_y
 }
 
-fn unique_ranges_at_y(sensors: &Vec<Sensor>, at_y: i32) -> Vec<RangeInclusive<i32>> {
-    let all_ranges_at_y: Vec<RangeInclusive<i32>> = sensors
+fn unique_ranges_at_y(sensors: &[Sensor], at_y: i32) -> Vec<RangeInclusive<i32>> {
+    sensors
         .iter()
-        .filter(|sensor| {
-            (sensor.at.1 - sensor.distance()..=sensor.at.1 + sensor.distance()).contains(&at_y)
+        .filter_map(|sensor| sensor.range_at_y(at_y))
+        .sorted_by_key(|range| *range.start())
+        .fold(vec![], |mut acc: Vec<RangeInclusive<i32>>, range| {
+            match acc.last_mut() {
+                Some(last_range) if *range.start() <= last_range.end() + 1 => {
+                    *last_range = *last_range.start()..=*range.end().max(last_range.end());
+                }
+                _ => acc.push(range),
+            }
+            acc
         })
-        .map(|sensor| {
-            let distance_to_y = (sensor.at.1 - at_y).abs();
-            let width_in_y = (sensor.distance() - distance_to_y).abs();
-            sensor.at.0 - width_in_y..=sensor.at.0 + width_in_y
+}
+
+#[allow(dead_code)]
+fn covered_positions(sensors: &[Sensor], area: &Area) -> usize {
+    (area.min.1..=area.max.1)
+        .map(|y| {
+            unique_ranges_at_y(sensors, y)
+                .into_iter()
+                .map(|range| {
+                    let start = *range.start().max(&area.min.0);
+                    let end = *range.end().min(&area.max.0);
+                    (start..=end).count()
+                })
+                .sum::<usize>()
         })
-        .sorted_by_key(|sensor| *sensor.start())
-        .collect();
+        .sum()
+}
 
-    all_ranges_at_y[1..]
+#[allow(dead_code)]
+fn uncovered_positions(sensors: &[Sensor], area: &Area) -> Vec<Point> {
+    let fully_covered_by_one = sensors
         .iter()
-        .fold(vec![all_ranges_at_y[0].clone()], |mut acc, range| {
-            let last_range = acc.last().unwrap();
-            if last_range.contains(range.start()) {
-                let last_range = *last_range.start()..=*range.end().max(last_range.end());
-                let last_index = acc.len() - 1;
-                acc[last_index] = last_range;
-                return acc;
-            } else {
-                acc.push(range.clone());
-            }
-            acc
+        .any(|sensor| area.corners().iter().all(|&corner| sensor.covers(corner)));
+
+    if fully_covered_by_one {
+        vec![]
+    } else if area.min == area.max {
+        vec![area.min]
+    } else {
+        area.quadrants()
+            .iter()
+            .flat_map(|quadrant| uncovered_positions(sensors, quadrant))
+            .collect()
+    }
+}
+
+fn distress_beacon(sensors: &[Sensor], area: &Area) -> Option<Point> {
+    // Each sensor is bordered by lines x + y = a and x - y = b just outside its reach
+    let (ascending, descending): (Vec<i32>, Vec<i32>) = sensors
+        .iter()
+        .flat_map(|sensor| {
+            let (x, y) = sensor.at;
+            let reach = sensor.distance() + 1;
+            [
+                (x + y - reach, x - y - reach),
+                (x + y + reach, x - y + reach),
+            ]
         })
+        .unzip();
+
+    let intersections = ascending
+        .iter()
+        .cartesian_product(descending.iter())
+        .filter(|(a, b)| (*a - *b) % 2 == 0)
+        .map(|(a, b)| ((a + b) / 2, (a - b) / 2));
+    let on_edges = ascending.iter().flat_map(|a| {
+        [
+            (area.min.0, a - area.min.0),
+            (area.max.0, a - area.max.0),
+            (a - area.min.1, area.min.1),
+            (a - area.max.1, area.max.1),
+        ]
+    });
+    let on_other_edges = descending.iter().flat_map(|b| {
+        [
+            (area.min.0, area.min.0 - b),
+            (area.max.0, area.max.0 - b),
+            (b + area.min.1, area.min.1),
+            (b + area.max.1, area.max.1),
+        ]
+    });
+
+    intersections
+        .chain(on_edges)
+        .chain(on_other_edges)
+        .chain(area.corners())
+        .filter(|&point| area.contains(point))
+        .find(|&point| sensors.iter().all(|sensor| !sensor.covers(point)))
 }
 
-fn tunning_frequency(sensors: &Vec<Sensor>, max: i32) -> usize {
-    for y in 0..=max {
-        let ranges = unique_ranges_at_y(sensors, y);
-        if ranges.len() != 1 {
-            println!("{} {:?}", y, ranges);
-            // sadly doing the math by hand after getting the line
-        }
-    }
-    0
+fn tunning_frequency(sensors: &[Sensor], area: &Area) -> Option<usize> {
+    distress_beacon(sensors, area).map(|(x, y)| x as usize * 4000000 + y as usize)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn parsing() {
-        let line = String::from("Sensor at x=2, y=18: closest beacon is at x=-2, y=15");
-        assert_eq!(
-            Sensor::from_str(&line),
-            Ok(Sensor {
-                at: (2, 18),
-                beacon: (-2, 15)
-            })
-        );
-    }
-
-    #[test]
-    fn sample_input_part_1() {
-        let sensors: Vec<Sensor> = vec![
+    fn sample_sensors() -> Vec<Sensor> {
+        vec![
             String::from("Sensor at x=2, y=18: closest beacon is at x=-2, y=15"),
             String::from("Sensor at x=9, y=16: closest beacon is at x=10, y=16"),
             String::from("Sensor at x=13, y=2: closest beacon is at x=15, y=3"),
@@ -139,11 +246,82 @@ mod tests {
         ]
         .iter()
         .map(|line| line.parse().unwrap())
-        .collect();
+        .collect()
+    }
+
+    #[test]
+    fn parsing() {
+        let line = String::from("Sensor at x=2, y=18: closest beacon is at x=-2, y=15");
+        assert_eq!(
+            Sensor::from_str(&line),
+            Ok(Sensor {
+                at: (2, 18),
+                beacon: (-2, 15)
+            })
+        );
+    }
+
+    #[test]
+    fn sample_input_part_1() {
+        let sensors = sample_sensors();
 
         assert_eq!(no_beacon_positions(&sensors, 10), 26);
     }
 
     #[test]
-    fn sample_input_part_2() {}
+    fn beacons_on_row_counted_exactly() {
+        let sensors: Vec<Sensor> = [
+            "Sensor at x=0, y=0: closest beacon is at x=2, y=0",
+            "Sensor at x=10, y=0: closest beacon is at x=8, y=0",
+            "Sensor at x=20, y=5: closest beacon is at x=20, y=3",
+        ]
+        .iter()
+        .map(|line| line.parse().unwrap())
+        .collect();
+
+        assert_eq!(unique_ranges_at_y(&sensors, 0), vec![-2..=2, 8..=12]);
+        assert_eq!(no_beacon_positions(&sensors, 0), 8);
+        assert_eq!(no_beacon_positions(&sensors, 3), 0);
+        assert_eq!(no_beacon_positions(&sensors, 100), 0);
+    }
+
+    #[test]
+    fn rectangle_coverage() {
+        let sensors = sample_sensors();
+        let area = Area::square(20);
+
+        assert_eq!(covered_positions(&sensors, &area), 21 * 21 - 1);
+        assert_eq!(uncovered_positions(&sensors, &area), vec![(14, 11)]);
+        assert_eq!(
+            uncovered_positions(
+                &sensors,
+                &Area {
+                    min: (0, 0),
+                    max: (10, 10)
+                }
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn sample_input_part_2() {
+        let sensors = sample_sensors();
+
+        assert_eq!(distress_beacon(&sensors, &Area::square(20)), Some((14, 11)));
+        assert_eq!(
+            tunning_frequency(&sensors, &Area::square(20)),
+            Some(56000011)
+        );
+        assert_eq!(
+            distress_beacon(
+                &sensors,
+                &Area {
+                    min: (0, 0),
+                    max: (10, 10)
+                }
+            ),
+            None
+        );
+    }
 }
@@ -1,9 +1,4 @@
-use std::{
-    collections::{HashMap, HashSet, VecDeque},
-    str::FromStr,
-};
-
-use itertools::Itertools;
+use std::{collections::HashMap, str::FromStr};
 
 use crate::parser;
 
@@ -40,119 +35,127 @@ impl FromStr for Valve {
     }
 }
 
-pub fn part1() -> usize {
-    let valves: Vec<Valve> = parser::read("data/day16.txt").unwrap();
-    most_pressure_released(&valves)
+#[derive(Debug, Clone)]
+struct Network {
+    flow_rates: Vec<usize>,
+    distances: Vec<Vec<usize>>,
+    start: usize,
 }
 
-pub fn part2() -> usize {
-    let valves: Vec<Valve> = parser::read("data/day16.txt").unwrap();
-    most_pressure_released_with_elephant(&valves)
-}
-
-fn most_pressure_released(valves: &Vec<Valve>) -> usize {
-    all_valid_paths(valves, 30)
-        .iter()
-        .map(|p| p.1)
-        .max()
-        .unwrap() as usize
-}
-
-fn most_pressure_released_with_elephant(valves: &Vec<Valve>) -> usize {
-    let mut max_pressure = 0;
-    let paths = all_valid_paths(valves, 26);
-    let valid_paths: Vec<&(HashSet<String>, usize)> =
-        paths.iter().sorted_by_key(|(_, flow)| flow).rev().collect();
-
-    for i in 0..valid_paths.len() {
-        for j in i..valid_paths.len() {
-            let (a_valves, a_flow) = valid_paths[i];
-            let (b_valves, b_flow) = valid_paths[j];
-
-            let pressure = a_flow + b_flow;
-            if pressure < max_pressure {
-                break;
-            }
-
-            if a_valves.iter().all(|v| !b_valves.contains(v)) && pressure > max_pressure {
-                max_pressure = pressure;
-            }
+impl Network {
+    fn new(valves: &[Valve]) -> Self {
+        let distances = distances_between_valves(valves);
+        let with_flow: Vec<usize> = (0..valves.len())
+            .filter(|&i| valves[i].flow_rate > 0)
+            .collect();
+        let start_valve = valves.iter().position(|valve| valve.name == "AA").unwrap();
+
+        // Valves with flow are bits 0..n of the opened mask, the start valve comes right after
+        let nodes: Vec<usize> = with_flow.iter().copied().chain([start_valve]).collect();
+
+        Network {
+            flow_rates: with_flow.iter().map(|&i| valves[i].flow_rate).collect(),
+            distances: nodes
+                .iter()
+                .map(|&from| nodes.iter().map(|&to| distances[from][to]).collect())
+                .collect(),
+            start: with_flow.len(),
         }
     }
 
-    max_pressure
-}
-
-fn all_valid_paths(valves: &Vec<Valve>, max_minutes: usize) -> Vec<(HashSet<String>, usize)> {
-    let mut all_paths = vec![];
-    let distances = distances_between_valves(&valves);
-
-    let mut valve_with_flow_lookup = vec![];
-    for valve in valves {
-        if valve.flow_rate > 0 {
-            valve_with_flow_lookup.push((valve.name.clone(), valve.flow_rate));
+    fn best_pressure_per_mask(&self, minutes: usize) -> Vec<usize> {
+        let valves = self.flow_rates.len();
+        let mut best = vec![0; 1 << valves];
+        let mut states: Vec<HashMap<(usize, usize), usize>> = vec![HashMap::new(); minutes + 1];
+        states[minutes].insert((self.start, 0), 0);
+
+        for time_left in (0..=minutes).rev() {
+            for ((position, mask), pressure) in std::mem::take(&mut states[time_left]) {
+                best[mask] = best[mask].max(pressure);
+
+                for valve in (0..valves).filter(|valve| mask & (1 << valve) == 0) {
+                    let cost = self.distances[position][valve] + 1;
+                    if cost < time_left {
+                        let opened_minutes = time_left - cost;
+                        let released = pressure + self.flow_rates[valve] * opened_minutes;
+                        let entry = states[opened_minutes]
+                            .entry((valve, mask | (1 << valve)))
+                            .or_default();
+                        *entry = (*entry).max(released);
+                    }
+                }
+            }
         }
-    }
 
-    let mut pressures: Vec<(String, HashSet<String>, usize, usize)> =
-        vec![("AA".to_string(), HashSet::new(), 0, 0)];
-
-    while pressures.len() > 0 {
-        let (current_valve, visited_valves, minutes, pressure) = pressures.pop().unwrap();
-        all_paths.push((visited_valves.clone(), pressure));
-
-        for (valve, flow_rate) in &valve_with_flow_lookup {
-            if !visited_valves.contains(valve) {
-                let valve_open_minutes = minutes + distances[&current_valve][valve] + 1;
-
-                if valve_open_minutes < max_minutes {
-                    let new_pressure = pressure + flow_rate * (max_minutes - valve_open_minutes);
-                    let mut next_visited = visited_valves.clone();
-                    next_visited.insert(valve.clone());
-                    pressures.push((
-                        valve.clone(),
-                        next_visited,
-                        valve_open_minutes,
-                        new_pressure,
-                    ));
+        // Spread each mask's best to its supersets, so best[mask] covers any subset of it
+        for valve in 0..valves {
+            for mask in 0..best.len() {
+                if mask & (1 << valve) != 0 {
+                    best[mask] = best[mask].max(best[mask ^ (1 << valve)]);
                 }
             }
         }
-    }
 
-    all_paths
-}
+        best
+    }
 
-fn distances_between_valves(valves: &Vec<Valve>) -> HashMap<String, HashMap<String, usize>> {
-    let mut distances = HashMap::new();
+    fn most_pressure(&self, minutes: usize, agents: usize) -> usize {
+        let single = self.best_pressure_per_mask(minutes);
+        let mut combined = vec![0; single.len()];
+
+        for _ in 0..agents {
+            combined = (0..single.len())
+                .map(|mask| {
+                    let mut best = combined[mask];
+                    let mut submask = mask;
+                    while submask > 0 {
+                        best = best.max(single[submask] + combined[mask ^ submask]);
+                        submask = (submask - 1) & mask;
+                    }
+                    best
+                })
+                .collect();
+        }
 
-    let mut valve_lookup = HashMap::new();
-    for valve in valves {
-        valve_lookup.insert(valve.name.clone(), valve);
+        combined.last().copied().unwrap_or(0)
     }
+}
 
-    for valve in valves {
-        let mut valve_distances = HashMap::new();
-        let mut visited_valves = HashSet::new();
-        let mut next_valves = VecDeque::new();
+pub fn part1() -> usize {
+    let valves: Vec<Valve> = parser::read("data/day16.txt").unwrap();
+    Network::new(&valves).most_pressure(30, 1)
+}
 
-        visited_valves.insert(valve.name.clone());
-        next_valves.push_back((valve.name.clone(), 0));
+pub fn part2() -> usize {
+    let valves: Vec<Valve> = parser::read("data/day16.txt").unwrap();
+    Network::new(&valves).most_pressure(26, 2)
+}
 
-        while visited_valves.len() < valves.len() {
-            let (current_valve, current_distance) = next_valves.pop_front().unwrap();
+fn distances_between_valves(valves: &[Valve]) -> Vec<Vec<usize>> {
+    let lookup: HashMap<&str, usize> = valves
+        .iter()
+        .enumerate()
+        .map(|(i, valve)| (valve.name.as_str(), i))
+        .collect();
+
+    let unreachable = usize::MAX / 2;
+    let mut distances = vec![vec![unreachable; valves.len()]; valves.len()];
+    for (i, valve) in valves.iter().enumerate() {
+        distances[i][i] = 0;
+        for tunnel in &valve.tunnels {
+            distances[i][lookup[tunnel.as_str()]] = 1;
+        }
+    }
 
-            for potential_next_valve in &valve_lookup.get(&current_valve).unwrap().tunnels {
-                if !visited_valves.contains(potential_next_valve) {
-                    let valve_distance = current_distance + 1;
-                    valve_distances.insert(potential_next_valve.clone(), valve_distance);
-                    visited_valves.insert(potential_next_valve.clone());
-                    next_valves.push_back((potential_next_valve.clone(), valve_distance));
+    for k in 0..valves.len() {
+        for i in 0..valves.len() {
+            for j in 0..valves.len() {
+                let through_k = distances[i][k] + distances[k][j];
+                if through_k < distances[i][j] {
+                    distances[i][j] = through_k;
                 }
             }
         }
-
-        distances.insert(valve.name.clone(), valve_distances);
     }
 
     distances
@@ -160,8 +163,6 @@ fn distances_between_valves(valves: &Vec<Valve>) -> HashMap<String, HashMap<Stri
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-
     use super::*;
 
     #[test]
@@ -186,94 +187,115 @@ mod tests {
 
     #[test]
     fn distances() {
-        let valves: Vec<Valve> = vec![
-            String::from("Valve AA has flow rate=0; tunnels lead to valves BB, DD"),
-            String::from("Valve BB has flow rate=13; tunnels lead to valves AA, CC, DD"),
-            String::from("Valve CC has flow rate=2; tunnels lead to valves BB, EE"),
-            String::from("Valve DD has flow rate=20; tunnels lead to valves AA, BB"),
-            String::from("Valve EE has flow rate=20; tunnel leads to valve CC"),
+        let valves: Vec<Valve> = [
+            "Valve AA has flow rate=0; tunnels lead to valves BB, DD",
+            "Valve BB has flow rate=13; tunnels lead to valves AA, CC, DD",
+            "Valve CC has flow rate=2; tunnels lead to valves BB, EE",
+            "Valve DD has flow rate=20; tunnels lead to valves AA, BB",
+            "Valve EE has flow rate=20; tunnel leads to valve CC",
         ]
         .iter()
         .map(|line| line.parse().unwrap())
         .collect();
 
-        let mut expected_distances: HashMap<String, HashMap<String, usize>> = HashMap::new();
-
-        let mut dists = HashMap::new();
-        dists.insert("BB".to_string(), 1);
-        dists.insert("CC".to_string(), 2);
-        dists.insert("DD".to_string(), 1);
-        dists.insert("EE".to_string(), 3);
-        expected_distances.insert("AA".to_string(), dists);
-
-        let mut dists = HashMap::new();
-        dists.insert("AA".to_string(), 1);
-        dists.insert("CC".to_string(), 1);
-        dists.insert("DD".to_string(), 1);
-        dists.insert("EE".to_string(), 2);
-        expected_distances.insert("BB".to_string(), dists);
-
-        let mut dists = HashMap::new();
-        dists.insert("AA".to_string(), 2);
-        dists.insert("BB".to_string(), 1);
-        dists.insert("DD".to_string(), 2);
-        dists.insert("EE".to_string(), 1);
-        expected_distances.insert("CC".to_string(), dists);
-
-        let mut dists = HashMap::new();
-        dists.insert("AA".to_string(), 1);
-        dists.insert("BB".to_string(), 1);
-        dists.insert("CC".to_string(), 2);
-        dists.insert("EE".to_string(), 3);
-        expected_distances.insert("DD".to_string(), dists);
-
-        let mut dists = HashMap::new();
-        dists.insert("AA".to_string(), 3);
-        dists.insert("BB".to_string(), 2);
-        dists.insert("CC".to_string(), 1);
-        dists.insert("DD".to_string(), 3);
-        expected_distances.insert("EE".to_string(), dists);
-
-        assert_eq!(distances_between_valves(&valves), expected_distances);
+        assert_eq!(
+            distances_between_valves(&valves),
+            vec![
+                vec![0, 1, 2, 1, 3],
+                vec![1, 0, 1, 1, 2],
+                vec![2, 1, 0, 2, 1],
+                vec![1, 1, 2, 0, 3],
+                vec![3, 2, 1, 3, 0],
+            ]
+        );
     }
 
-    #[test]
-    fn sample_input_part_1() {
-        let valves: Vec<Valve> = vec![
-            String::from("Valve AA has flow rate=0; tunnels lead to valves DD, II, BB"),
-            String::from("Valve BB has flow rate=13; tunnels lead to valves CC, AA"),
-            String::from("Valve CC has flow rate=2; tunnels lead to valves DD, BB"),
-            String::from("Valve DD has flow rate=20; tunnels lead to valves CC, AA, EE"),
-            String::from("Valve EE has flow rate=3; tunnels lead to valves FF, DD"),
-            String::from("Valve FF has flow rate=0; tunnels lead to valves EE, GG"),
-            String::from("Valve GG has flow rate=0; tunnels lead to valves FF, HH"),
-            String::from("Valve HH has flow rate=22; tunnel leads to valve GG"),
-            String::from("Valve II has flow rate=0; tunnels lead to valves AA, JJ"),
-            String::from("Valve JJ has flow rate=21; tunnel leads to valve II"),
+    fn sample_valves() -> Vec<Valve> {
+        [
+            "Valve AA has flow rate=0; tunnels lead to valves DD, II, BB",
+            "Valve BB has flow rate=13; tunnels lead to valves CC, AA",
+            "Valve CC has flow rate=2; tunnels lead to valves DD, BB",
+            "Valve DD has flow rate=20; tunnels lead to valves CC, AA, EE",
+            "Valve EE has flow rate=3; tunnels lead to valves FF, DD",
+            "Valve FF has flow rate=0; tunnels lead to valves EE, GG",
+            "Valve GG has flow rate=0; tunnels lead to valves FF, HH",
+            "Valve HH has flow rate=22; tunnel leads to valve GG",
+            "Valve II has flow rate=0; tunnels lead to valves AA, JJ",
+            "Valve JJ has flow rate=21; tunnel leads to valve II",
         ]
         .iter()
         .map(|line| line.parse().unwrap())
-        .collect();
-        assert_eq!(most_pressure_released(&valves), 1651);
+        .collect()
+    }
+
+    #[test]
+    fn compressed_network() {
+        let network = Network::new(&sample_valves());
+
+        assert_eq!(network.flow_rates, vec![13, 2, 20, 3, 22, 21]);
+        assert_eq!(network.start, 6);
+        assert_eq!(network.distances[6], vec![1, 2, 1, 2, 5, 2, 0]);
+    }
+
+    // Tries every order of opening the valves in the mask, for a single agent
+    fn best_route(network: &Network, position: usize, time_left: usize, mask: usize) -> usize {
+        (0..network.flow_rates.len())
+            .filter(|valve| mask & (1 << valve) != 0)
+            .filter_map(|valve| {
+                let opened_minutes =
+                    time_left.checked_sub(network.distances[position][valve] + 1)?;
+                Some(
+                    network.flow_rates[valve] * opened_minutes
+                        + best_route(network, valve, opened_minutes, mask ^ (1 << valve)),
+                )
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Hands every valve to one of the agents or to nobody, and routes each agent alone
+    fn brute_force(network: &Network, minutes: usize, agents: usize) -> usize {
+        let valves = network.flow_rates.len();
+        (0..(agents + 1).pow(valves as u32))
+            .map(|assignment| {
+                (0..agents)
+                    .map(|agent| {
+                        let mask = (0..valves)
+                            .filter(|&valve| {
+                                assignment / (agents + 1).pow(valve as u32) % (agents + 1)
+                                    == agent + 1
+                            })
+                            .fold(0, |mask, valve| mask | (1 << valve));
+                        best_route(network, network.start, minutes, mask)
+                    })
+                    .sum()
+            })
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn any_number_of_agents() {
+        let network = Network::new(&sample_valves());
+
+        assert_eq!(network.most_pressure(0, 1), 0);
+        assert_eq!(network.most_pressure(30, 0), 0);
+        assert_eq!(brute_force(&network, 30, 1), 1651);
+        assert_eq!(brute_force(&network, 26, 2), 1707);
+
+        assert_eq!(brute_force(&network, 20, 3), 1308);
+        assert_eq!(network.most_pressure(20, 3), 1308);
+        assert!(network.most_pressure(20, 3) > network.most_pressure(20, 2));
+        assert_eq!(network.most_pressure(26, 6), network.most_pressure(26, 7));
+    }
+
+    #[test]
+    fn sample_input_part_1() {
+        assert_eq!(Network::new(&sample_valves()).most_pressure(30, 1), 1651);
     }
 
     #[test]
     fn sample_input_part_2() {
-        let valves: Vec<Valve> = vec![
-            String::from("Valve AA has flow rate=0; tunnels lead to valves DD, II, BB"),
-            String::from("Valve BB has flow rate=13; tunnels lead to valves CC, AA"),
-            String::from("Valve CC has flow rate=2; tunnels lead to valves DD, BB"),
-            String::from("Valve DD has flow rate=20; tunnels lead to valves CC, AA, EE"),
-            String::from("Valve EE has flow rate=3; tunnels lead to valves FF, DD"),
-            String::from("Valve FF has flow rate=0; tunnels lead to valves EE, GG"),
-            String::from("Valve GG has flow rate=0; tunnels lead to valves FF, HH"),
-            String::from("Valve HH has flow rate=22; tunnel leads to valve GG"),
-            String::from("Valve II has flow rate=0; tunnels lead to valves AA, JJ"),
-            String::from("Valve JJ has flow rate=21; tunnel leads to valve II"),
-        ]
-        .iter()
-        .map(|line| line.parse().unwrap())
-        .collect();
-        assert_eq!(most_pressure_released_with_elephant(&valves), 1707);
+        assert_eq!(Network::new(&sample_valves()).most_pressure(26, 2), 1707);
     }
 }
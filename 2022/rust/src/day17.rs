@@ -1,7 +1,28 @@
-use std::ops::Neg;
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+};
 
 use crate::parser;
 
+const STANDARD_ROCKS: &str = "####
+
+.#.
+###
+.#.
+
+..#
+..#
+###
+
+#
+#
+#
+#
+
+##
+##";
+
 #[derive(Debug, Clone, PartialEq)]
 enum Move {
     Left,
@@ -20,236 +41,312 @@ fn parse(line: &str) -> Vec<Move> {
         .collect()
 }
 
-#[derive(Debug, Clone)]
-struct Shape {
-    positions: Vec<(i32, i32)>,
+#[derive(Debug, Clone, PartialEq)]
+struct Rock {
+    rows: Vec<u64>,
 }
 
-impl Shape {
-    fn from(positions: Vec<(i32, i32)>) -> Self {
-        Self { positions }
-    }
+#[derive(Debug, Clone, PartialEq)]
+struct ParseRockError;
+
+impl FromStr for Rock {
+    type Err = ParseRockError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s
+            .lines()
+            .rev()
+            .map(|line| {
+                line.chars().enumerate().try_fold(0, |row, (x, c)| match c {
+                    '#' => 1u64
+                        .checked_shl(x as u32)
+                        .map(|bit| row | bit)
+                        .ok_or(ParseRockError),
+                    '.' => Ok(row),
+                    _ => Err(ParseRockError),
+                })
+            })
+            .collect::<Result<Vec<u64>, _>>()?;
 
-    fn move_by(&mut self, (dx, dy): (i32, i32)) {
-        self.positions.iter_mut().for_each(|position| {
-            position.0 += dx;
-            position.1 += dy;
-        });
+        if rows.is_empty() || rows.contains(&0) {
+            return Err(ParseRockError);
+        }
+        Ok(Rock { rows })
     }
+}
 
-    fn collides(&self, board: &Vec<Vec<bool>>) -> bool {
-        self.positions.iter().any(|(x, y)| {
-            let out_of_bounds = *x < 0 || *x > 6 || *y < 0;
-            let occupied = board
-                .get(*y as usize)
-                .and_then(|row| row.get(*x as usize))
-                .map(|v| *v)
-                .unwrap_or(false);
-            out_of_bounds || occupied
-        })
-    }
+#[derive(Debug, Clone)]
+struct ChamberConfig {
+    rocks: Vec<Rock>,
+    width: usize,
+    spawn_left: usize,
+    spawn_gap: usize,
+}
 
-    fn transfer_to(&self, board: &mut Vec<Vec<bool>>) {
-        for (x, y) in &self.positions {
-            if *y as usize == board.len() {
-                board.push(vec![false; 7]);
-            }
-            board[*y as usize][*x as usize] = true;
+impl Default for ChamberConfig {
+    fn default() -> Self {
+        ChamberConfig {
+            rocks: parse_rocks(STANDARD_ROCKS).unwrap(),
+            width: 7,
+            spawn_left: 2,
+            spawn_gap: 3,
         }
     }
+}
 
-    fn digits_from(&self, board: &Vec<Vec<bool>>) -> Vec<usize> {
-        let ys: Vec<i32> = self.positions.iter().map(|p| p.1).collect();
-        let ymin = ys.iter().min().unwrap().clone();
-        let ymax = ys.iter().max().unwrap().clone();
-
-        (ymin..=ymax)
-            .map(|y| {
-                let row = board.get(y as usize).unwrap();
-                let mut height = 0;
-                for (i, &cell) in row.iter().enumerate() {
-                    if cell {
-                        height |= 1 << i;
-                    }
-                }
-                height
-            })
-            .collect()
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ChamberError {
+    NoRocks,
+    NoJets,
+    InvalidWidth(usize),
+    RockTooWide(usize),
+}
+
+impl ChamberConfig {
+    // Rows are u64 bit sets, and each rock has to spawn fully inside the walls
+    fn validate(&self) -> Result<(), ChamberError> {
+        if !(1..=64).contains(&self.width) {
+            return Err(ChamberError::InvalidWidth(self.width));
+        }
+        if self.rocks.is_empty() {
+            return Err(ChamberError::NoRocks);
+        }
+
+        let bits = |row: &u64| 64 - row.leading_zeros() as usize;
+        match self.rocks.iter().position(|rock| {
+            rock.rows
+                .iter()
+                .any(|row| bits(row) + self.spawn_left > self.width)
+        }) {
+            Some(index) => Err(ChamberError::RockTooWide(index)),
+            None => Ok(()),
+        }
     }
 }
 
-pub fn part1() -> usize {
-    let lines: Vec<String> = parser::read("data/day17.txt").unwrap();
-    let moves = parse(&lines[0]);
-    let board = board_after_rocks(&moves, 2022);
-    board.len()
+fn parse_rocks(raw: &str) -> Result<Vec<Rock>, ParseRockError> {
+    raw.split("\n\n").map(str::parse).collect()
 }
 
-pub fn part2() -> usize {
-    let lines: Vec<String> = parser::read("data/day17.txt").unwrap();
-    let moves = parse(&lines[0]);
-    simulated_height(&moves, 1000000000000)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cycle {
+    start: usize,
+    length: usize,
+    height_gain: usize,
+}
+
+struct Chamber<'a> {
+    config: &'a ChamberConfig,
+    jets: &'a [Move],
+    rows: Vec<u64>,
+    pruned_height: usize,
+    rock_index: usize,
+    jet_index: usize,
+    rocks: usize,
 }
 
-fn board_after_rocks(moves: &Vec<Move>, limit: usize) -> Vec<Vec<bool>> {
-    let mut moves = moves.iter().cycle();
-    let mut board: Vec<Vec<bool>> = vec![];
+impl<'a> Chamber<'a> {
+    fn new(config: &'a ChamberConfig, jets: &'a [Move]) -> Result<Self, ChamberError> {
+        config.validate()?;
+        if jets.is_empty() {
+            return Err(ChamberError::NoJets);
+        }
+
+        Ok(Chamber {
+            config,
+            jets,
+            rows: vec![],
+            pruned_height: 0,
+            rock_index: 0,
+            jet_index: 0,
+            rocks: 0,
+        })
+    }
+
+    fn height(&self) -> usize {
+        self.pruned_height + self.rows.len()
+    }
 
-    let shapes = vec![
-        Shape::from(vec![(2, 0), (3, 0), (4, 0), (5, 0)]),
-        Shape::from(vec![(3, 0), (2, 1), (3, 1), (4, 1), (3, 2)]),
-        Shape::from(vec![(2, 0), (3, 0), (4, 0), (4, 1), (4, 2)]),
-        Shape::from(vec![(2, 0), (2, 1), (2, 2), (2, 3)]),
-        Shape::from(vec![(2, 0), (3, 0), (2, 1), (3, 1)]),
-    ];
-    let mut shapes = shapes.iter().cycle();
-    let mut count = 0;
+    // The rightmost column, as shifting past it would drop cells at a width of 64
+    fn right_wall(&self) -> u64 {
+        1 << (self.config.width - 1)
+    }
+
+    fn collides(&self, rock: &[u64], y: isize) -> bool {
+        rock.iter().enumerate().any(|(i, row)| {
+            let ry = y + i as isize;
+            ry < 0
+                || self
+                    .rows
+                    .get(ry as usize)
+                    .is_some_and(|cells| cells & row != 0)
+        })
+    }
 
-    while count < limit {
-        let mut shape = shapes.next().unwrap().clone();
-        shape.move_by((0, (board.len() + 3) as i32));
+    fn drop_rock(&mut self) {
+        let mut rock: Vec<u64> = self.config.rocks[self.rock_index]
+            .rows
+            .iter()
+            .map(|row| row << self.config.spawn_left)
+            .collect();
+        let mut y = (self.rows.len() + self.config.spawn_gap) as isize;
+        self.rock_index = (self.rock_index + 1) % self.config.rocks.len();
 
         loop {
-            let vertical_move = match moves.next().unwrap() {
-                Move::Left => (-1, 0),
-                Move::Right => (1, 0),
+            let jet = &self.jets[self.jet_index];
+            self.jet_index = (self.jet_index + 1) % self.jets.len();
+
+            let pushed: Option<Vec<u64>> = match jet {
+                Move::Left if rock.iter().all(|row| row & 1 == 0) => {
+                    Some(rock.iter().map(|row| row >> 1).collect())
+                }
+                Move::Right if rock.iter().all(|row| row & self.right_wall() == 0) => {
+                    Some(rock.iter().map(|row| row << 1).collect())
+                }
+                _ => None,
             };
-            shape.move_by(vertical_move);
-            if shape.collides(&board) {
-                shape.move_by((vertical_move.0.neg(), 0));
+            if let Some(pushed) = pushed.filter(|pushed| !self.collides(pushed, y)) {
+                rock = pushed;
             }
 
-            shape.move_by((0, -1));
-            if shape.collides(&board) {
-                shape.move_by((0, 1));
-                shape.transfer_to(&mut board);
+            if self.collides(&rock, y - 1) {
                 break;
             }
+            y -= 1;
         }
 
-        count += 1;
+        for (i, row) in rock.iter().enumerate() {
+            let ry = y as usize + i;
+            if ry == self.rows.len() {
+                self.rows.push(0);
+            }
+            self.rows[ry] |= row;
+        }
+        self.rocks += 1;
+        self.prune();
     }
 
-    board
-}
-
-fn simulated_height(moves: &Vec<Move>, limit: usize) -> usize {
-    let (cycle_heights, start, pre_cycle_height) = find_cycle(moves);
-    let remaining_limit = limit - start;
-
-    let cycle_height = cycle_heights.last().unwrap();
-    let total_cycles = remaining_limit / cycle_heights.len();
-    let cycle_i = remaining_limit & cycle_heights.len();
-    let remaining_cycle_height = cycle_heights.get(cycle_i).unwrap();
-
-    pre_cycle_height + (cycle_height * total_cycles) + remaining_cycle_height
-}
-
-fn find_cycle(moves: &Vec<Move>) -> (Vec<usize>, usize, usize) {
-    let mut moves = moves.iter().enumerate().cycle();
-    let mut board: Vec<Vec<bool>> = vec![];
-
-    let shapes = vec![
-        Shape::from(vec![(2, 0), (3, 0), (4, 0), (5, 0)]),
-        Shape::from(vec![(3, 0), (2, 1), (3, 1), (4, 1), (3, 2)]),
-        Shape::from(vec![(2, 0), (3, 0), (4, 0), (4, 1), (4, 2)]),
-        Shape::from(vec![(2, 0), (2, 1), (2, 2), (2, 3)]),
-        Shape::from(vec![(2, 0), (3, 0), (2, 1), (3, 1)]),
-    ];
-    let mut shapes = shapes.iter().cycle();
-
-    let mut entries = vec![];
-    let mut cycle_start = 0;
+    fn prune(&mut self) {
+        // Flood the air from the open row above the top, anything under the lowest
+        // reachable row can never be touched again so it is treated as solid
+        let top = self.rows.len();
+        let mut reached = vec![0u64; top + 1];
+        let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
+        for x in 0..self.config.width {
+            reached[top] |= 1 << x;
+            queue.push_back((x, top));
+        }
 
-    while cycle_start == 0 {
-        let mut shape = shapes.next().unwrap().clone();
-        shape.move_by((0, (board.len() + 3) as i32));
+        let mut lowest = top;
+        while let Some((x, y)) = queue.pop_front() {
+            lowest = lowest.min(y);
 
-        loop {
-            let (move_index, next_move) = moves.next().unwrap();
-            let vertical_move = match next_move {
-                Move::Left => (-1, 0),
-                Move::Right => (1, 0),
-            };
-            shape.move_by(vertical_move);
-            if shape.collides(&board) {
-                shape.move_by((vertical_move.0.neg(), 0));
+            let mut neighbours = vec![];
+            if x > 0 {
+                neighbours.push((x - 1, y));
+            }
+            if x + 1 < self.config.width {
+                neighbours.push((x + 1, y));
+            }
+            if y > 0 {
+                neighbours.push((x, y - 1));
+            }
+            if y < top {
+                neighbours.push((x, y + 1));
             }
 
-            shape.move_by((0, -1));
-            if shape.collides(&board) {
-                shape.move_by((0, 1));
-                shape.transfer_to(&mut board);
-
-                let height = board.len();
-                let digits = shape.digits_from(&board);
-                // println!("{}: {} - {:?}", height, move_index, digits);
-
-                let first_match_index =
-                    entries
-                        .iter()
-                        .position(|(_, prev_move_index, prev_digits)| {
-                            *prev_move_index == move_index && *prev_digits == digits
-                        });
-
-                if let Some(index) = first_match_index {
-                    cycle_start = index;
-                } else {
-                    entries.push((height, move_index, digits));
+            for (nx, ny) in neighbours {
+                let bit = 1 << nx;
+                let free = ny == top || self.rows[ny] & bit == 0;
+                if free && reached[ny] & bit == 0 {
+                    reached[ny] |= bit;
+                    queue.push_back((nx, ny));
                 }
-
-                break;
             }
         }
+
+        self.rows.drain(..lowest);
+        self.pruned_height += lowest;
     }
 
-    let pre_cycle_height = entries.get(cycle_start - 1).unwrap().0;
-    let heights: Vec<usize> = (cycle_start..entries.len())
-        .map(|i| {
-            let entry = entries.get(i).unwrap();
-            entry.0 - pre_cycle_height
-        })
-        .collect();
+    fn state(&self) -> (usize, usize, Vec<u64>) {
+        (self.rock_index, self.jet_index, self.rows.clone())
+    }
 
-    (heights, cycle_start + 1, pre_cycle_height)
-}
+    fn find_cycle(&mut self, max_rocks: usize) -> Option<Cycle> {
+        let mut seen: HashMap<(usize, usize, Vec<u64>), (usize, usize)> = HashMap::new();
 
-#[allow(dead_code)]
-fn print(board: &Vec<Vec<bool>>) {
-    for y in (0..board.len()).rev() {
-        let line: String = board[y]
+        while self.rocks < max_rocks {
+            if let Some((start, height)) = seen.insert(self.state(), (self.rocks, self.height())) {
+                return Some(Cycle {
+                    start,
+                    length: self.rocks - start,
+                    height_gain: self.height() - height,
+                });
+            }
+            self.drop_rock();
+        }
+
+        None
+    }
+
+    #[allow(dead_code)]
+    fn render(&self) -> String {
+        let mut lines: Vec<String> = self
+            .rows
             .iter()
-            .map(|p| match p {
-                true => '#',
-                false => '.',
+            .rev()
+            .map(|row| {
+                let cells: String = (0..self.config.width)
+                    .map(|x| if row & (1 << x) != 0 { '#' } else { '.' })
+                    .collect();
+                format!("|{}|", cells)
             })
             .collect();
-        println!("{}", line);
+
+        let floor = if self.pruned_height == 0 { '-' } else { '~' };
+        lines.push(format!("+{}+", floor.to_string().repeat(self.config.width)));
+        lines.join("\n")
     }
 }
 
-#[allow(dead_code)]
-fn print_as_digits(board: &Vec<Vec<bool>>) {
-    let mut digits: Vec<usize> = vec![];
-    for row in board {
-        let mut height = 0;
-        for (i, &cell) in row.iter().enumerate() {
-            if cell {
-                height |= 1 << i;
+pub fn part1() -> usize {
+    let lines: Vec<String> = parser::read("data/day17.txt").unwrap();
+    let moves = parse(&lines[0]);
+    height_after(&ChamberConfig::default(), &moves, 2022).unwrap()
+}
+
+pub fn part2() -> usize {
+    let lines: Vec<String> = parser::read("data/day17.txt").unwrap();
+    let moves = parse(&lines[0]);
+    height_after(&ChamberConfig::default(), &moves, 1000000000000).unwrap()
+}
+
+fn height_after(
+    config: &ChamberConfig,
+    moves: &[Move],
+    rocks: usize,
+) -> Result<usize, ChamberError> {
+    let mut chamber = Chamber::new(config, moves)?;
+
+    Ok(match chamber.find_cycle(rocks) {
+        None => chamber.height(),
+        Some(cycle) => {
+            let remaining = rocks - chamber.rocks;
+            for _ in 0..remaining % cycle.length {
+                chamber.drop_rock();
             }
+            chamber.height() + (remaining / cycle.length) * cycle.height_gain
         }
-        digits.push(height);
-    }
-
-    for d in digits {
-        println!("{d}");
-    }
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const SAMPLE_JETS: &str = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>";
+
     #[test]
     fn parsing() {
         let line = "<<><>";
@@ -259,16 +356,164 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parsing_rocks() {
+        assert_eq!(
+            ".#.\n###\n.#.".parse::<Rock>(),
+            Ok(Rock {
+                rows: vec![0b010, 0b111, 0b010]
+            })
+        );
+        assert_eq!(
+            "..#\n..#\n###".parse::<Rock>(),
+            Ok(Rock {
+                rows: vec![0b111, 0b100, 0b100]
+            })
+        );
+        assert_eq!("#x".parse::<Rock>(), Err(ParseRockError));
+        assert_eq!(ChamberConfig::default().rocks.len(), 5);
+    }
+
+    #[test]
+    fn rendering() {
+        let config = ChamberConfig::default();
+        let moves = parse(SAMPLE_JETS);
+        let mut chamber = Chamber::new(&config, &moves).unwrap();
+
+        chamber.drop_rock();
+        assert_eq!(chamber.render(), "|..####.|\n+-------+");
+
+        chamber.drop_rock();
+        assert_eq!(
+            chamber.render(),
+            [
+                "|...#...|",
+                "|..###..|",
+                "|...#...|",
+                "|..####.|",
+                "+-------+"
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn pruning() {
+        let config = ChamberConfig::default();
+        let moves = parse(SAMPLE_JETS);
+        let mut chamber = Chamber::new(&config, &moves).unwrap();
+
+        for _ in 0..2022 {
+            chamber.drop_rock();
+        }
+        assert_eq!(chamber.height(), 3068);
+        assert!(chamber.rows.len() < 100);
+    }
+
+    #[test]
+    fn cycle_detection() {
+        let config = ChamberConfig::default();
+        let moves = parse(SAMPLE_JETS);
+        let cycle = Chamber::new(&config, &moves)
+            .unwrap()
+            .find_cycle(10000)
+            .unwrap();
+
+        assert_eq!(cycle.length, 35);
+        assert_eq!(cycle.height_gain, 53);
+        assert_eq!(
+            Chamber::new(&config, &moves)
+                .unwrap()
+                .find_cycle(cycle.start),
+            None
+        );
+    }
+
+    #[test]
+    fn custom_chamber() {
+        let config = ChamberConfig {
+            rocks: parse_rocks("#\n\n##").unwrap(),
+            width: 2,
+            spawn_left: 0,
+            spawn_gap: 1,
+        };
+        let moves = parse("<");
+
+        assert_eq!(height_after(&config, &moves, 4), Ok(4));
+        assert_eq!(
+            height_after(&config, &moves, 1000000000001),
+            Ok(1000000000001)
+        );
+    }
+
+    #[test]
+    fn widest_chamber() {
+        let config = ChamberConfig {
+            rocks: parse_rocks("##").unwrap(),
+            width: 64,
+            spawn_left: 62,
+            spawn_gap: 3,
+        };
+        let moves = parse(">");
+        let mut chamber = Chamber::new(&config, &moves).unwrap();
+
+        chamber.drop_rock();
+        chamber.drop_rock();
+        assert_eq!(chamber.height(), 2);
+        assert_eq!(chamber.rows, vec![0b11 << 62; 2]);
+    }
+
+    #[test]
+    fn invalid_chambers() {
+        let moves = parse("<");
+        let chamber_error = |config: ChamberConfig| height_after(&config, &moves, 10).err();
+
+        for width in [0, 65] {
+            assert_eq!(
+                chamber_error(ChamberConfig {
+                    width,
+                    ..ChamberConfig::default()
+                }),
+                Some(ChamberError::InvalidWidth(width))
+            );
+        }
+        assert_eq!(
+            chamber_error(ChamberConfig {
+                spawn_left: 4,
+                ..ChamberConfig::default()
+            }),
+            Some(ChamberError::RockTooWide(0))
+        );
+        assert_eq!(
+            chamber_error(ChamberConfig {
+                rocks: vec![],
+                ..ChamberConfig::default()
+            }),
+            Some(ChamberError::NoRocks)
+        );
+        assert_eq!(
+            height_after(&ChamberConfig::default(), &[], 10),
+            Err(ChamberError::NoJets)
+        );
+        assert_eq!(ChamberConfig::default().validate(), Ok(()));
+        assert_eq!("#".repeat(65).parse::<Rock>(), Err(ParseRockError));
+    }
+
     #[test]
     fn sample_input_part_1() {
-        let moves = parse(">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>");
-        let board = board_after_rocks(&moves, 2022);
-        assert_eq!(board.len(), 3068);
+        let moves = parse(SAMPLE_JETS);
+        assert_eq!(
+            height_after(&ChamberConfig::default(), &moves, 2022),
+            Ok(3068)
+        );
     }
 
     #[test]
     fn sample_input_part_2() {
-        let moves = parse(">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>");
-        assert_eq!(simulated_height(&moves, 1000000000000), 1514285714288);
+        let moves = parse(SAMPLE_JETS);
+        assert_eq!(
+            height_after(&ChamberConfig::default(), &moves, 1000000000000),
+            Ok(1514285714288)
+        );
     }
 }
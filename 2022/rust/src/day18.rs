@@ -2,108 +2,203 @@ use std::collections::{HashSet, VecDeque};
 
 use crate::parser;
 
-pub fn part1() -> usize {
-    let lines: Vec<String> = parser::read("data/day18.txt").unwrap();
-    let cubes = parse(lines);
-    surface_area(&cubes)
+type Voxel = (i32, i32, i32);
+
+const DIRECTIONS: [Voxel; 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct VoxelSet {
+    voxels: HashSet<Voxel>,
 }
 
-pub fn part2() -> usize {
-    let lines: Vec<String> = parser::read("data/day18.txt").unwrap();
-    let cubes = parse(lines);
-    outside_surface_area(&cubes)
+impl FromIterator<Voxel> for VoxelSet {
+    fn from_iter<T: IntoIterator<Item = Voxel>>(iter: T) -> Self {
+        VoxelSet {
+            voxels: iter.into_iter().collect(),
+        }
+    }
 }
 
-fn surface_area(cubes: &Vec<(usize, usize, usize)>) -> usize {
-    let mut area = 0;
-    let mut previous = HashSet::new();
-    let diffs: Vec<(isize, isize, isize)> = vec![
-        (1, 0, 0),
-        (-1, 0, 0),
-        (0, 1, 0),
-        (0, -1, 0),
-        (0, 0, 1),
-        (0, 0, -1),
-    ];
-
-    for cube in cubes {
-        area += 6;
-        let (x, y, z) = *cube;
-
-        for (x_diff, y_diff, z_diff) in &diffs {
-            if previous.contains(&(
-                x.wrapping_add_signed(*x_diff),
-                y.wrapping_add_signed(*y_diff),
-                z.wrapping_add_signed(*z_diff),
-            )) {
-                area -= 2;
+#[allow(dead_code)]
+impl VoxelSet {
+    fn len(&self) -> usize {
+        self.voxels.len()
+    }
+
+    fn contains(&self, voxel: &Voxel) -> bool {
+        self.voxels.contains(voxel)
+    }
+
+    fn bounds(&self) -> Option<(Voxel, Voxel)> {
+        let min = |axis: fn(&Voxel) -> i32| self.voxels.iter().map(axis).min();
+        let max = |axis: fn(&Voxel) -> i32| self.voxels.iter().map(axis).max();
+
+        Some((
+            (min(|v| v.0)?, min(|v| v.1)?, min(|v| v.2)?),
+            (max(|v| v.0)?, max(|v| v.1)?, max(|v| v.2)?),
+        ))
+    }
+
+    fn surface_area(&self) -> usize {
+        self.voxels
+            .iter()
+            .flat_map(|&voxel| neighbours(voxel))
+            .filter(|neighbour| !self.contains(neighbour))
+            .count()
+    }
+
+    fn components(&self) -> Vec<VoxelSet> {
+        connected_components(self.voxels.iter().copied(), |voxel| self.contains(voxel))
+    }
+
+    fn air_pockets(&self) -> Vec<VoxelSet> {
+        let Some((min, max)) = self.bounds() else {
+            return vec![];
+        };
+        let outside = self.exterior_air();
+        let trapped = (min.0..=max.0)
+            .flat_map(|x| {
+                (min.1..=max.1).flat_map(move |y| (min.2..=max.2).map(move |z| (x, y, z)))
+            })
+            .filter(|voxel| !self.contains(voxel) && !outside.contains(voxel));
+
+        connected_components(trapped, |voxel| {
+            !self.contains(voxel) && !outside.contains(voxel) && in_bounds(voxel, min, max)
+        })
+    }
+
+    fn exterior_air(&self) -> VoxelSet {
+        let Some((min, max)) = self.bounds() else {
+            return VoxelSet::default();
+        };
+        let min = (min.0 - 1, min.1 - 1, min.2 - 1);
+        let max = (max.0 + 1, max.1 + 1, max.2 + 1);
+
+        let mut steamed = HashSet::from([min]);
+        let mut path = VecDeque::from([min]);
+        while let Some(voxel) = path.pop_front() {
+            for neighbour in neighbours(voxel) {
+                if in_bounds(&neighbour, min, max)
+                    && !self.contains(&neighbour)
+                    && steamed.insert(neighbour)
+                {
+                    path.push_back(neighbour);
+                }
             }
         }
 
-        previous.insert(cube);
+        VoxelSet { voxels: steamed }
+    }
+
+    fn interior_surface_area(&self) -> usize {
+        self.air_pockets()
+            .iter()
+            .map(|pocket| pocket.surface_area())
+            .sum()
     }
 
-    area
+    fn exterior_surface_area(&self) -> usize {
+        self.surface_area() - self.interior_surface_area()
+    }
+
+    fn render_slices(&self) -> String {
+        let Some((min, max)) = self.bounds() else {
+            return String::new();
+        };
+        let trapped: VoxelSet = self
+            .air_pockets()
+            .into_iter()
+            .flat_map(|pocket| pocket.voxels)
+            .collect();
+
+        (min.2..=max.2)
+            .map(|z| {
+                let rows: Vec<String> = (min.1..=max.1)
+                    .map(|y| {
+                        (min.0..=max.0)
+                            .map(|x| {
+                                if self.contains(&(x, y, z)) {
+                                    '#'
+                                } else if trapped.contains(&(x, y, z)) {
+                                    'o'
+                                } else {
+                                    '.'
+                                }
+                            })
+                            .collect()
+                    })
+                    .collect();
+                format!("z={}\n{}", z, rows.join("\n"))
+            })
+            .collect::<Vec<String>>()
+            .join("\n\n")
+    }
 }
 
-fn outside_surface_area(cubes: &Vec<(usize, usize, usize)>) -> usize {
-    let cubes: HashSet<_> = cubes
+fn neighbours((x, y, z): Voxel) -> impl Iterator<Item = Voxel> {
+    DIRECTIONS
         .iter()
-        .cloned()
-        .map(|(x, y, z)| (x + 1, y + 1, z + 1))
-        .collect();
+        .map(move |(dx, dy, dz)| (x + dx, y + dy, z + dz))
+}
 
-    let max_coord = cubes
-        .iter()
-        .flat_map(|(x, y, z)| vec![x, y, z])
-        .max()
-        .unwrap()
-        + 1;
-
-    let mut steamed: HashSet<(usize, usize, usize)> = HashSet::new();
-    steamed.insert((0, 0, 0));
-    let mut path: VecDeque<(usize, usize, usize)> = VecDeque::new();
-    path.push_back((0, 0, 0));
-
-    let mut area = 0;
-
-    let directions = [
-        (1, 0, 0),
-        (-1, 0, 0),
-        (0, 1, 0),
-        (0, -1, 0),
-        (0, 0, 1),
-        (0, 0, -1),
-    ];
-    while let Some((x, y, z)) = path.pop_front() {
-        for (x_dir, y_dir, z_dir) in directions {
-            let x_new = x.checked_add_signed(x_dir).unwrap_or(0).min(max_coord);
-            let y_new = y.checked_add_signed(y_dir).unwrap_or(0).min(max_coord);
-            let z_new = z.checked_add_signed(z_dir).unwrap_or(0).min(max_coord);
-            let new_node = (x_new, y_new, z_new);
-
-            if cubes.contains(&new_node) {
-                area += 1;
-                continue;
-            }
+fn in_bounds(voxel: &Voxel, min: Voxel, max: Voxel) -> bool {
+    (min.0..=max.0).contains(&voxel.0)
+        && (min.1..=max.1).contains(&voxel.1)
+        && (min.2..=max.2).contains(&voxel.2)
+}
 
-            if steamed.contains(&new_node) {
-                continue;
-            }
+fn connected_components(
+    voxels: impl Iterator<Item = Voxel>,
+    belongs: impl Fn(&Voxel) -> bool,
+) -> Vec<VoxelSet> {
+    let mut seen: HashSet<Voxel> = HashSet::new();
+    let mut components = vec![];
 
-            steamed.insert(new_node);
-            path.push_back(new_node);
+    for start in voxels {
+        if !seen.insert(start) {
+            continue;
         }
+
+        let mut component = HashSet::from([start]);
+        let mut path = VecDeque::from([start]);
+        while let Some(voxel) = path.pop_front() {
+            for neighbour in neighbours(voxel) {
+                if belongs(&neighbour) && seen.insert(neighbour) {
+                    component.insert(neighbour);
+                    path.push_back(neighbour);
+                }
+            }
+        }
+        components.push(VoxelSet { voxels: component });
     }
 
-    area
+    components
 }
 
-fn parse(input: Vec<String>) -> Vec<(usize, usize, usize)> {
+pub fn part1() -> usize {
+    let lines: Vec<String> = parser::read("data/day18.txt").unwrap();
+    let cubes: VoxelSet = parse(lines).into_iter().collect();
+    cubes.surface_area()
+}
+
+pub fn part2() -> usize {
+    let lines: Vec<String> = parser::read("data/day18.txt").unwrap();
+    let cubes: VoxelSet = parse(lines).into_iter().collect();
+    cubes.exterior_surface_area()
+}
+
+fn parse(input: Vec<String>) -> Vec<Voxel> {
     input
         .into_iter()
         .map(|line| {
-            let parts: Vec<usize> = line.split(',').map(|part| part.parse().unwrap()).collect();
+            let parts: Vec<i32> = line.split(',').map(|part| part.parse().unwrap()).collect();
             (parts[0], parts[1], parts[2])
         })
         .collect()
@@ -113,42 +208,67 @@ fn parse(input: Vec<String>) -> Vec<(usize, usize, usize)> {
 mod tests {
     use super::*;
 
-    #[test]
-    fn sample_input_part_1() {
-        let input = vec![
+    fn sample_droplet() -> VoxelSet {
+        let input = [
             "2,2,2", "1,2,2", "3,2,2", "2,1,2", "2,3,2", "2,2,1", "2,2,3", "2,2,4", "2,2,6",
             "1,2,5", "3,2,5", "2,1,5", "2,3,5",
         ];
         let input = input.iter().map(|s| s.to_string()).collect();
-        let cubes = parse(input);
+        parse(input).into_iter().collect()
+    }
 
-        let result = surface_area(&cubes);
+    #[test]
+    fn signed_coordinates() {
+        let cubes: VoxelSet = parse(vec!["-1,0,0".to_string(), "0,0,0".to_string()])
+            .into_iter()
+            .collect();
 
-        assert_eq!(result, 64);
+        assert_eq!(cubes.bounds(), Some(((-1, 0, 0), (0, 0, 0))));
+        assert_eq!(cubes.surface_area(), 10);
+        assert_eq!(cubes.exterior_surface_area(), 10);
     }
 
     #[test]
-    fn sample_input_part_2() {
-        let input = vec![
-            "2,2,2", "1,2,2", "3,2,2", "2,1,2", "2,3,2", "2,2,1", "2,2,3", "2,2,4", "2,2,6",
-            "1,2,5", "3,2,5", "2,1,5", "2,3,5",
-        ];
-        let input = input.iter().map(|s| s.to_string()).collect();
-        let cubes = parse(input);
+    fn components_and_pockets() {
+        let cubes = sample_droplet();
+        let pockets = cubes.air_pockets();
+
+        assert_eq!(cubes.components().len(), 6);
+        assert_eq!(pockets.len(), 1);
+        assert_eq!(pockets[0].len(), 1);
+        assert!(pockets[0].contains(&(2, 2, 5)));
+        assert_eq!(cubes.interior_surface_area(), 6);
+    }
+
+    #[test]
+    fn rendering() {
+        let cubes: VoxelSet = parse(vec!["0,0,0".to_string(), "1,1,1".to_string()])
+            .into_iter()
+            .collect();
 
-        let result = outside_surface_area(&cubes);
+        assert_eq!(cubes.components().len(), 2);
+        assert_eq!(cubes.render_slices(), "z=0\n#.\n..\n\nz=1\n..\n.#");
+        assert!(sample_droplet()
+            .render_slices()
+            .contains("z=5\n.#.\n#o#\n.#."));
+    }
+
+    #[test]
+    fn sample_input_part_1() {
+        assert_eq!(sample_droplet().surface_area(), 64);
+    }
 
-        assert_eq!(result, 58);
+    #[test]
+    fn sample_input_part_2() {
+        assert_eq!(sample_droplet().exterior_surface_area(), 58);
     }
 
     #[test]
     fn sample_input_part_2_extra() {
-        let input = vec!["1,2,2", "2,2,2", "3,2,2", "3,2,1", "4,3,2"];
+        let input = ["1,2,2", "2,2,2", "3,2,2", "3,2,1", "4,3,2"];
         let input = input.iter().map(|s| s.to_string()).collect();
-        let cubes = parse(input);
-
-        let result = outside_surface_area(&cubes);
+        let cubes: VoxelSet = parse(input).into_iter().collect();
 
-        assert_eq!(result, 24);
+        assert_eq!(cubes.exterior_surface_area(), 24);
     }
 }
@@ -1,177 +1,189 @@
 use regex::Regex;
-use std::{
-    collections::{HashSet, VecDeque},
-    str::FromStr,
-};
+use std::str::FromStr;
 
 use crate::parser;
 
-#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
-struct Simulation {
-    blueprint: Blueprint,
-    materials: (usize, usize, usize, usize),
-    robots: (usize, usize, usize, usize),
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Recipe {
+    robot: usize,
+    costs: Vec<(usize, usize)>,
 }
 
-impl Simulation {
-    fn from(blueprint: Blueprint) -> Self {
-        Self {
-            blueprint,
-            materials: (0, 0, 0, 0),
-            robots: (1, 0, 0, 0),
-        }
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Blueprint {
+    id: usize,
+    resources: Vec<String>,
+    recipes: Vec<Recipe>,
+}
 
-    fn build_ore_robot(&self) -> Option<(Self, usize)> {
-        let ore_cost = self.blueprint.ore_robot_cost;
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Plan {
+    geodes: usize,
+    build_order: Vec<(usize, String)>,
+}
 
-        let mut turns = 0;
-        let mut collected = self.clone();
-        while collected.materials.0 < ore_cost {
-            collected = collected.collect();
-            turns += 1;
-        }
-        collected = collected.collect();
-        turns += 1;
-
-        let mut materials = collected.materials;
-        materials.0 -= ore_cost;
-        let mut robots = collected.robots;
-        robots.0 += 1;
-        Some((
-            Simulation {
-                blueprint: collected.blueprint,
-                materials,
-                robots,
-            },
-            turns,
-        ))
+impl Blueprint {
+    fn resource(&self, name: &str) -> Option<usize> {
+        self.resources.iter().position(|resource| resource == name)
     }
 
-    fn build_clay_robot(&self) -> Option<(Self, usize)> {
-        let ore_cost = self.blueprint.clay_robot_cost;
+    fn optimise(&self, minutes: usize) -> Plan {
+        let target = self
+            .resource("geode")
+            .unwrap_or(self.resources.len().saturating_sub(1));
 
-        let mut turns = 0;
-        let mut collected = self.clone();
-        while collected.materials.0 < ore_cost {
-            collected = collected.collect();
-            turns += 1;
+        let mut max_useful = vec![0; self.resources.len()];
+        for recipe in &self.recipes {
+            for &(resource, cost) in &recipe.costs {
+                max_useful[resource] = max_useful[resource].max(cost);
+            }
+        }
+        max_useful[target] = usize::MAX;
+
+        let mut search = Search {
+            blueprint: self,
+            minutes,
+            target,
+            max_useful,
+            best: 0,
+            best_order: vec![],
+        };
+        let mut robots = vec![0; self.resources.len()];
+        if let Some(first) = self.recipes.first() {
+            robots[first.robot] = 1;
+        }
+        search.branch(0, vec![0; self.resources.len()], robots, &mut vec![]);
+
+        Plan {
+            geodes: search.best,
+            build_order: search
+                .best_order
+                .into_iter()
+                .map(|(minute, robot)| (minute, self.resources[robot].clone()))
+                .collect(),
         }
-        collected = collected.collect();
-        turns += 1;
-
-        let mut materials = collected.materials;
-        materials.0 -= ore_cost;
-        let mut robots = collected.robots;
-        robots.1 += 1;
-        Some((
-            Simulation {
-                blueprint: collected.blueprint,
-                materials,
-                robots,
-            },
-            turns,
-        ))
     }
+}
 
-    fn build_obisidian_robot(&self) -> Option<(Self, usize)> {
-        let (ore_cost, clay_cost) = self.blueprint.obsidian_robot_cost;
+struct Search<'a> {
+    blueprint: &'a Blueprint,
+    minutes: usize,
+    target: usize,
+    max_useful: Vec<usize>,
+    best: usize,
+    best_order: Vec<(usize, usize)>,
+}
 
-        if self.robots.1 == 0 {
-            return None;
+impl Search<'_> {
+    fn branch(
+        &mut self,
+        time: usize,
+        resources: Vec<usize>,
+        robots: Vec<usize>,
+        order: &mut Vec<(usize, usize)>,
+    ) {
+        let remaining = self.minutes - time;
+        let idle = resources[self.target] + robots[self.target] * remaining;
+        if idle > self.best {
+            self.best = idle;
+            self.best_order = order.clone();
         }
 
-        let mut turns = 0;
-        let mut collected = self.clone();
-        while collected.materials.0 < ore_cost || collected.materials.1 < clay_cost {
-            collected = collected.collect();
-            turns += 1;
+        // Optimistic bound: a new target robot every remaining minute
+        let upper_bound = idle + remaining * remaining.saturating_sub(1) / 2;
+        if upper_bound <= self.best {
+            return;
         }
-        collected = collected.collect();
-        turns += 1;
-
-        let mut materials = collected.materials;
-        materials.0 -= ore_cost;
-        materials.1 -= clay_cost;
-        let mut robots = collected.robots;
-        robots.2 += 1;
-        Some((
-            Simulation {
-                blueprint: collected.blueprint,
-                materials,
-                robots,
-            },
-            turns,
-        ))
-    }
 
-    fn build_geode_robot(&self) -> Option<(Self, usize)> {
-        let (ore_cost, obsidian_cost) = self.blueprint.geode_robot_cost;
+        for recipe in self.blueprint.recipes.iter().rev() {
+            if robots[recipe.robot] >= self.max_useful[recipe.robot] {
+                continue;
+            }
 
-        if self.robots.2 == 0 {
-            return None;
-        }
+            let wait = recipe.costs.iter().try_fold(0, |wait, &(resource, cost)| {
+                if cost <= resources[resource] {
+                    Some(wait)
+                } else if robots[resource] == 0 {
+                    None
+                } else {
+                    Some(wait.max((cost - resources[resource]).div_ceil(robots[resource])))
+                }
+            });
+            let Some(wait) = wait else {
+                continue;
+            };
+            let elapsed = wait + 1;
+            if time + elapsed >= self.minutes {
+                continue;
+            }
 
-        let mut turns = 0;
-        let mut collected = self.clone();
-        while collected.materials.0 < ore_cost || collected.materials.2 < obsidian_cost {
-            collected = collected.collect();
-            turns += 1;
-        }
-        collected = collected.collect();
-        turns += 1;
-
-        let mut materials = collected.materials;
-        materials.0 -= ore_cost;
-        materials.2 -= obsidian_cost;
-        let mut robots = collected.robots;
-        robots.3 += 1;
-        Some((
-            Simulation {
-                blueprint: collected.blueprint,
-                materials,
-                robots,
-            },
-            turns,
-        ))
-    }
+            let mut next_resources: Vec<usize> = resources
+                .iter()
+                .zip(&robots)
+                .map(|(amount, rate)| amount + rate * elapsed)
+                .collect();
+            for &(resource, cost) in &recipe.costs {
+                next_resources[resource] -= cost;
+            }
+            let mut next_robots = robots.clone();
+            next_robots[recipe.robot] += 1;
 
-    fn collect(&self) -> Self {
-        Self {
-            blueprint: self.blueprint,
-            materials: (
-                self.materials.0 + self.robots.0,
-                self.materials.1 + self.robots.1,
-                self.materials.2 + self.robots.2,
-                self.materials.3 + self.robots.3,
-            ),
-            robots: self.robots,
+            order.push((time + elapsed, recipe.robot));
+            self.branch(time + elapsed, next_resources, next_robots, order);
+            order.pop();
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
-struct Blueprint {
-    id: usize,
-    ore_robot_cost: usize,
-    clay_robot_cost: usize,
-    obsidian_robot_cost: (usize, usize),
-    geode_robot_cost: (usize, usize),
-}
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ParseBlueprintError(String);
 
 impl FromStr for Blueprint {
-    type Err = String;
+    type Err = ParseBlueprintError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let re = Regex::new(r"Blueprint (\d+): Each ore robot costs (\d+) ore. Each clay robot costs (\d+) ore. Each obsidian robot costs (\d+) ore and (\d+) clay. Each geode robot costs (\d+) ore and (\d+) obsidian.").unwrap();
-        let caps = re.captures(s).unwrap();
+        let id_re = Regex::new(r"^Blueprint (\d+):").unwrap();
+        let robot_re = Regex::new(r"Each (\w+) robot costs ([^.]+)\.").unwrap();
+        let cost_re = Regex::new(r"^(\d+) (\w+)$").unwrap();
+
+        let id = id_re
+            .captures(s)
+            .and_then(|caps| caps[1].parse().ok())
+            .ok_or_else(|| ParseBlueprintError(format!("missing blueprint id in '{s}'")))?;
+
+        let mut resources: Vec<String> = vec![];
+        let mut index_of = |name: &str| {
+            resources
+                .iter()
+                .position(|resource| resource == name)
+                .unwrap_or_else(|| {
+                    resources.push(name.to_string());
+                    resources.len() - 1
+                })
+        };
+
+        let mut recipes = vec![];
+        for caps in robot_re.captures_iter(s) {
+            let robot = index_of(&caps[1]);
+            let costs = caps[2]
+                .split(" and ")
+                .map(|cost| {
+                    let cost_caps = cost_re
+                        .captures(cost.trim())
+                        .ok_or_else(|| ParseBlueprintError(format!("invalid cost '{cost}'")))?;
+                    Ok((index_of(&cost_caps[2]), cost_caps[1].parse().unwrap()))
+                })
+                .collect::<Result<_, _>>()?;
+            recipes.push(Recipe { robot, costs });
+        }
 
+        if recipes.is_empty() {
+            return Err(ParseBlueprintError(format!("no robots in '{s}'")));
+        }
         Ok(Blueprint {
-            id: caps[1].parse().unwrap(),
-            ore_robot_cost: caps[2].parse().unwrap(),
-            clay_robot_cost: caps[3].parse().unwrap(),
-            obsidian_robot_cost: (caps[4].parse().unwrap(), caps[5].parse().unwrap()),
-            geode_robot_cost: (caps[6].parse().unwrap(), caps[7].parse().unwrap()),
+            id,
+            resources,
+            recipes,
         })
     }
 }
@@ -186,93 +198,33 @@ pub fn part2() -> usize {
     mult_quality_levels(&blueprints)
 }
 
-fn sum_quality_levels(blueprints: &Vec<Blueprint>) -> usize {
+fn sum_quality_levels(blueprints: &[Blueprint]) -> usize {
     blueprints
         .iter()
-        .map(|blueprint| {
-            let simulation = Simulation::from(blueprint.clone());
-            let quality_level = simulate_quality_level(simulation, 24);
-            quality_level * blueprint.id
-        })
+        .map(|blueprint| blueprint.optimise(24).geodes * blueprint.id)
         .sum()
 }
 
-fn mult_quality_levels(blueprints: &Vec<Blueprint>) -> usize {
+fn mult_quality_levels(blueprints: &[Blueprint]) -> usize {
     blueprints
         .iter()
         .take(3)
-        .map(|blueprint| {
-            let simulation = Simulation::from(blueprint.clone());
-            simulate_quality_level(simulation, 32)
-        })
-        .fold(1, |acc, q| acc * q)
-}
-
-fn simulate_quality_level(start: Simulation, max_minutes: usize) -> usize {
-    let mut max_geodes = 0;
-
-    let mut visited = HashSet::new();
-    visited.insert((start, 0));
-    let mut paths = VecDeque::new();
-    paths.push_back((start, 0));
-
-    while let Some((simulation, minutes)) = paths.pop_front() {
-        if minutes >= max_minutes {
-            continue;
-        }
-
-        let geodes_building_nothing_else =
-            simulation.materials.3 + (max_minutes - minutes) * simulation.robots.3;
-        max_geodes = max_geodes.max(geodes_building_nothing_else);
-
-        let mut candidates = HashSet::new();
-        if let Some(next) = simulation.build_geode_robot() {
-            candidates.insert(next);
-        }
-        if let Some(next) = simulation.build_obisidian_robot() {
-            candidates.insert(next);
-        }
-        if let Some(next) = simulation.build_clay_robot() {
-            candidates.insert(next);
-        }
-        if let Some(next) = simulation.build_ore_robot() {
-            candidates.insert(next);
-        }
-
-        for (candidate, turns) in candidates {
-            let candidate_minutes = turns + minutes;
-            let new_state = (candidate, candidate_minutes);
-            if candidate_minutes >= max_minutes || visited.contains(&new_state) {
-                continue;
-            }
-
-            let remaining_time = max_minutes - candidate_minutes;
-            let candidate_potential = candidate.materials.3
-                + remaining_time * candidate.robots.3
-                + remaining_time * (remaining_time - 1) / 2;
-            if candidate_potential <= max_geodes {
-                continue;
-            }
-
-            visited.insert(new_state);
-            paths.push_back(new_state);
-        }
-    }
-
-    max_geodes
+        .map(|blueprint| blueprint.optimise(32).geodes)
+        .product()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const SAMPLE: [&str; 2] = [
+        "Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. Each obsidian robot costs 3 ore and 14 clay. Each geode robot costs 2 ore and 7 obsidian.",
+        "Blueprint 2: Each ore robot costs 2 ore. Each clay robot costs 3 ore. Each obsidian robot costs 3 ore and 8 clay. Each geode robot costs 3 ore and 12 obsidian.",
+    ];
+
     #[test]
     fn sample_input_part_1() {
-        let input = vec![
-            "Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. Each obsidian robot costs 3 ore and 14 clay. Each geode robot costs 2 ore and 7 obsidian.",
-            "Blueprint 2: Each ore robot costs 2 ore. Each clay robot costs 3 ore. Each obsidian robot costs 3 ore and 8 clay. Each geode robot costs 3 ore and 12 obsidian.",
-        ];
-        let blueprints: Vec<Blueprint> = input.iter().map(|s| s.parse().unwrap()).collect();
+        let blueprints: Vec<Blueprint> = SAMPLE.iter().map(|s| s.parse().unwrap()).collect();
 
         let result = sum_quality_levels(&blueprints);
 
@@ -280,5 +232,63 @@ mod tests {
     }
 
     #[test]
-    fn sample_input_part_2() {}
+    fn parsing() {
+        let blueprint: Blueprint = SAMPLE[0].parse().unwrap();
+
+        assert_eq!(blueprint.id, 1);
+        assert_eq!(blueprint.resources, ["ore", "clay", "obsidian", "geode"]);
+        assert_eq!(
+            blueprint.recipes[2],
+            Recipe {
+                robot: 2,
+                costs: vec![(0, 3), (1, 14)]
+            }
+        );
+        assert!("Blueprint 1: nothing here.".parse::<Blueprint>().is_err());
+    }
+
+    #[test]
+    fn build_order() {
+        let blueprint: Blueprint = SAMPLE[0].parse().unwrap();
+        let plan = blueprint.optimise(24);
+
+        assert_eq!(plan.geodes, 9);
+        assert_eq!(
+            plan.build_order
+                .iter()
+                .filter(|(_, robot)| robot == "geode")
+                .count(),
+            2
+        );
+        assert!(plan
+            .build_order
+            .windows(2)
+            .all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn generic_resources() {
+        let blueprint: Blueprint =
+            "Blueprint 7: Each wood robot costs 1 wood. Each geode robot costs 2 wood."
+                .parse()
+                .unwrap();
+
+        assert_eq!(blueprint.resources, ["wood", "geode"]);
+        assert_eq!(
+            blueprint.optimise(5),
+            Plan {
+                geodes: 2,
+                build_order: vec![(3, "geode".to_string())]
+            }
+        );
+    }
+
+    #[test]
+    fn sample_input_part_2() {
+        let blueprints: Vec<Blueprint> = SAMPLE.iter().map(|s| s.parse().unwrap()).collect();
+
+        assert_eq!(blueprints[0].optimise(32).geodes, 56);
+        assert_eq!(blueprints[1].optimise(32).geodes, 62);
+        assert_eq!(mult_quality_levels(&blueprints), 56 * 62);
+    }
 }
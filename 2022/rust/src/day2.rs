@@ -1,57 +1,96 @@
 use std::str::FromStr;
 
+use itertools::Itertools;
+
 use crate::parser;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Shape {
     Rock,
     Paper,
     Scissor,
 }
 
+const SHAPES: [Shape; 3] = [Shape::Rock, Shape::Paper, Shape::Scissor];
+
+impl Shape {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    // Every shape beats the one just before it in the cycle Rock -> Paper -> Scissor
+    fn shifted(self, offset: usize) -> Shape {
+        SHAPES[(self.index() + offset) % 3]
+    }
+
+    #[allow(dead_code)]
+    fn beats(self) -> Shape {
+        self.shifted(2)
+    }
+
+    #[allow(dead_code)]
+    fn loses_to(self) -> Shape {
+        self.shifted(1)
+    }
+
+    fn score(self) -> usize {
+        self.index() + 1
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Outcome {
     Lose,
     Draw,
     Win,
 }
 
-struct PlayResponse(Shape, Shape);
+const OUTCOMES: [Outcome; 3] = [Outcome::Lose, Outcome::Draw, Outcome::Win];
 
-#[derive(Debug, Clone)]
-struct ParsePlayError;
+impl Outcome {
+    fn of(response: Shape, opponent: Shape) -> Outcome {
+        OUTCOMES[(response.index() + 4 - opponent.index()) % 3]
+    }
 
-impl FromStr for PlayResponse {
-    type Err = ParsePlayError;
+    fn response_to(self, opponent: Shape) -> Shape {
+        opponent.shifted(self as usize + 2)
+    }
 
-    fn from_str(s: &str) -> Result<Self, ParsePlayError> {
-        let mut splitted_line = s.split_whitespace();
-        let opponent = splitted_line.next().and_then(|v| match v {
-            "A" => Some(Shape::Rock),
-            "B" => Some(Shape::Paper),
-            "C" => Some(Shape::Scissor),
-            _ => None,
-        });
-        let response = splitted_line.next().and_then(|v| match v {
-            "X" => Some(Shape::Rock),
-            "Y" => Some(Shape::Paper),
-            "Z" => Some(Shape::Scissor),
-            _ => None,
-        });
-        match (opponent, response) {
-            (Some(opponent), Some(response)) => Ok(PlayResponse(opponent, response)),
-            _ => Err(ParsePlayError),
-        }
+    fn score(self) -> usize {
+        3 * self as usize
     }
 }
 
-struct PlayOutcome(Shape, Outcome);
+// How the second column of the guide (X, Y, Z) is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Interpretation {
+    Shapes([Shape; 3]),
+    Outcomes,
+}
+
+const STANDARD_SHAPES: Interpretation =
+    Interpretation::Shapes([Shape::Rock, Shape::Paper, Shape::Scissor]);
 
-#[derive(Debug, Clone)]
-struct ParsePlayOutcomeError;
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Goal {
+    Maximise,
+    Minimise,
+}
 
-impl FromStr for PlayOutcome {
-    type Err = ParsePlayOutcomeError;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Round {
+    opponent: Shape,
+    column: usize,
+}
 
-    fn from_str(s: &str) -> Result<Self, ParsePlayOutcomeError> {
+#[derive(Debug, Clone, PartialEq)]
+struct ParseRoundError;
+
+impl FromStr for Round {
+    type Err = ParseRoundError;
+
+    fn from_str(s: &str) -> Result<Self, ParseRoundError> {
         let mut splitted_line = s.split_whitespace();
         let opponent = splitted_line.next().and_then(|v| match v {
             "A" => Some(Shape::Rock),
@@ -59,103 +98,128 @@ impl FromStr for PlayOutcome {
             "C" => Some(Shape::Scissor),
             _ => None,
         });
-        let outcome = splitted_line.next().and_then(|v| match v {
-            "X" => Some(Outcome::Lose),
-            "Y" => Some(Outcome::Draw),
-            "Z" => Some(Outcome::Win),
+        let column = splitted_line.next().and_then(|v| match v {
+            "X" => Some(0),
+            "Y" => Some(1),
+            "Z" => Some(2),
             _ => None,
         });
-        match (opponent, outcome) {
-            (Some(opponent), Some(outcome)) => Ok(PlayOutcome(opponent, outcome)),
-            _ => Err(ParsePlayOutcomeError),
+        match (opponent, column, splitted_line.next()) {
+            (Some(opponent), Some(column), None) => Ok(Round { opponent, column }),
+            _ => Err(ParseRoundError),
         }
     }
 }
 
-pub fn part1() -> usize {
-    let strategy_guide: Vec<PlayResponse> = parser::read("data/day2.txt").unwrap();
-    total_score(&strategy_guide)
-}
+impl Round {
+    fn response(&self, interpretation: &Interpretation) -> Shape {
+        match interpretation {
+            Interpretation::Shapes(mapping) => mapping[self.column],
+            Interpretation::Outcomes => OUTCOMES[self.column].response_to(self.opponent),
+        }
+    }
 
-fn total_score(strategy_guide: &Vec<PlayResponse>) -> usize {
-    strategy_guide.iter().map(|play| score(play)).sum()
+    fn score(&self, interpretation: &Interpretation) -> usize {
+        let response = self.response(interpretation);
+        response.score() + Outcome::of(response, self.opponent).score()
+    }
 }
 
-fn score(play: &PlayResponse) -> usize {
-    let selected_shaped_score = match play {
-        PlayResponse(_, Shape::Rock) => 1,
-        PlayResponse(_, Shape::Paper) => 2,
-        PlayResponse(_, Shape::Scissor) => 3,
-    };
-
-    let outcome_score = match play {
-        PlayResponse(Shape::Rock, Shape::Paper) => 6,
-        PlayResponse(Shape::Paper, Shape::Scissor) => 6,
-        PlayResponse(Shape::Scissor, Shape::Rock) => 6,
-        PlayResponse(Shape::Rock, Shape::Rock) => 3,
-        PlayResponse(Shape::Paper, Shape::Paper) => 3,
-        PlayResponse(Shape::Scissor, Shape::Scissor) => 3,
-        _ => 0,
-    };
-
-    selected_shaped_score + outcome_score
+pub fn part1() -> usize {
+    let strategy_guide: Vec<Round> = parser::read("data/day2.txt").unwrap();
+    total_score(&strategy_guide, &STANDARD_SHAPES)
 }
 
 pub fn part2() -> usize {
-    let strategy_guide: Vec<PlayOutcome> = parser::read("data/day2.txt").unwrap();
-    total_score_from_outcomes(&strategy_guide)
+    let strategy_guide: Vec<Round> = parser::read("data/day2.txt").unwrap();
+    total_score(&strategy_guide, &Interpretation::Outcomes)
 }
 
-fn total_score_from_outcomes(strategy_guide: &Vec<PlayOutcome>) -> usize {
+fn total_score(strategy_guide: &[Round], interpretation: &Interpretation) -> usize {
     strategy_guide
         .iter()
-        .map(|play| score_from_outcome(play))
+        .map(|round| round.score(interpretation))
         .sum()
 }
 
-fn score_from_outcome(play: &PlayOutcome) -> usize {
-    let outcome_score = match play {
-        PlayOutcome(_, Outcome::Lose) => 0,
-        PlayOutcome(_, Outcome::Draw) => 3,
-        PlayOutcome(_, Outcome::Win) => 6,
-    };
-
-    let shape_score = match play {
-        PlayOutcome(Shape::Rock, Outcome::Lose) => 3,
-        PlayOutcome(Shape::Rock, Outcome::Draw) => 1,
-        PlayOutcome(Shape::Rock, Outcome::Win) => 2,
-        PlayOutcome(Shape::Paper, Outcome::Lose) => 1,
-        PlayOutcome(Shape::Paper, Outcome::Draw) => 2,
-        PlayOutcome(Shape::Paper, Outcome::Win) => 3,
-        PlayOutcome(Shape::Scissor, Outcome::Lose) => 2,
-        PlayOutcome(Shape::Scissor, Outcome::Draw) => 3,
-        PlayOutcome(Shape::Scissor, Outcome::Win) => 1,
-    };
-
-    outcome_score + shape_score
+// Tries the 6 ways of reading X, Y, Z as shapes and keeps the best one for the goal
+#[allow(dead_code)]
+fn search_mapping(strategy_guide: &[Round], goal: Goal) -> ([Shape; 3], usize) {
+    let scored = SHAPES.iter().permutations(3).map(|shapes| {
+        let mapping = [*shapes[0], *shapes[1], *shapes[2]];
+        (
+            mapping,
+            total_score(strategy_guide, &Interpretation::Shapes(mapping)),
+        )
+    });
+
+    match goal {
+        Goal::Maximise => scored.max_by_key(|&(_, score)| score),
+        Goal::Minimise => scored.min_by_key(|&(_, score)| score),
+    }
+    .unwrap()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn sample_guide() -> Vec<Round> {
+        ["A Y", "B X", "C Z"]
+            .iter()
+            .map(|line| line.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn shape_algebra() {
+        for shape in SHAPES {
+            assert_eq!(shape.beats().loses_to(), shape);
+            assert_eq!(Outcome::of(shape, shape.beats()), Outcome::Win);
+            assert_eq!(Outcome::of(shape, shape.loses_to()), Outcome::Lose);
+            assert_eq!(Outcome::of(shape, shape), Outcome::Draw);
+            for outcome in OUTCOMES {
+                assert_eq!(Outcome::of(outcome.response_to(shape), shape), outcome);
+            }
+        }
+        assert_eq!(Shape::Rock.beats(), Shape::Scissor);
+        assert_eq!(Shape::Rock.loses_to(), Shape::Paper);
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(
+            "C X".parse::<Round>(),
+            Ok(Round {
+                opponent: Shape::Scissor,
+                column: 0
+            })
+        );
+        assert_eq!("D X".parse::<Round>(), Err(ParseRoundError));
+        assert_eq!("A X Y".parse::<Round>(), Err(ParseRoundError));
+    }
+
+    #[test]
+    fn mapping_search() {
+        let guide = sample_guide();
+
+        assert_eq!(
+            search_mapping(&guide, Goal::Maximise),
+            ([Shape::Scissor, Shape::Paper, Shape::Rock], 24)
+        );
+        assert_eq!(
+            search_mapping(&guide, Goal::Minimise),
+            ([Shape::Rock, Shape::Scissor, Shape::Paper], 6)
+        );
+    }
+
     #[test]
     fn sample_input_total_score() {
-        let strategy_guide = vec![
-            PlayResponse(Shape::Rock, Shape::Paper),
-            PlayResponse(Shape::Paper, Shape::Rock),
-            PlayResponse(Shape::Scissor, Shape::Scissor),
-        ];
-        assert_eq!(total_score(&strategy_guide), 15);
+        assert_eq!(total_score(&sample_guide(), &STANDARD_SHAPES), 15);
     }
 
     #[test]
     fn sample_input_total_score_from_outcomes() {
-        let strategy_guide = vec![
-            PlayOutcome(Shape::Rock, Outcome::Draw),
-            PlayOutcome(Shape::Paper, Outcome::Lose),
-            PlayOutcome(Shape::Scissor, Outcome::Win),
-        ];
-        assert_eq!(total_score_from_outcomes(&strategy_guide), 12);
+        assert_eq!(total_score(&sample_guide(), &Interpretation::Outcomes), 12);
     }
 }
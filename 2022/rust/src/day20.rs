@@ -1,68 +1,296 @@
 use crate::parser;
 
-pub fn part1() -> usize {
-    let numbers: Vec<isize> = parser::read("data/day20.txt").unwrap();
-    sum_after_mixing(&numbers, 1, 1)
+const NIL: usize = usize::MAX;
+
+#[derive(Debug, Clone)]
+struct Node {
+    value: i64,
+    priority: u64,
+    left: usize,
+    right: usize,
+    parent: usize,
+    size: usize,
 }
 
-pub fn part2() -> usize {
-    let numbers: Vec<isize> = parser::read("data/day20.txt").unwrap();
-    sum_after_mixing(&numbers, 811589153, 10)
+// Implicit treap: nodes keep their original index, the tree order is the circle order
+#[derive(Debug, Clone)]
+struct MixingList {
+    nodes: Vec<Node>,
+    root: usize,
 }
 
-fn sum_after_mixing(numbers: &Vec<isize>, decryption_key: usize, times: usize) -> usize {
-    let mut mixed: Vec<(usize, isize)> = numbers
-        .iter()
-        .map(|n| n * decryption_key as isize)
-        .enumerate()
-        .clone()
-        .collect();
+impl MixingList {
+    fn new(values: &[i64]) -> Self {
+        let mut seed: u64 = 0x9E3779B97F4A7C15;
+        let nodes = values
+            .iter()
+            .map(|&value| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                Node {
+                    value,
+                    priority: seed,
+                    left: NIL,
+                    right: NIL,
+                    parent: NIL,
+                    size: 1,
+                }
+            })
+            .collect();
+
+        let mut list = MixingList { nodes, root: NIL };
+        for i in 0..list.nodes.len() {
+            list.root = list.merge(list.root, i);
+        }
+        list.detach(list.root);
+        list
+    }
+
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn size(&self, node: usize) -> usize {
+        if node == NIL {
+            0
+        } else {
+            self.nodes[node].size
+        }
+    }
+
+    fn detach(&mut self, node: usize) {
+        if node != NIL {
+            self.nodes[node].parent = NIL;
+        }
+    }
+
+    fn update(&mut self, node: usize) {
+        let (left, right) = (self.nodes[node].left, self.nodes[node].right);
+        self.nodes[node].size = 1 + self.size(left) + self.size(right);
+        if left != NIL {
+            self.nodes[left].parent = node;
+        }
+        if right != NIL {
+            self.nodes[right].parent = node;
+        }
+    }
+
+    fn split(&mut self, node: usize, count: usize) -> (usize, usize) {
+        if node == NIL {
+            return (NIL, NIL);
+        }
+
+        let left = self.nodes[node].left;
+        if self.size(left) >= count {
+            let (a, b) = self.split(left, count);
+            self.nodes[node].left = b;
+            self.update(node);
+            (a, node)
+        } else {
+            let right = self.nodes[node].right;
+            let (a, b) = self.split(right, count - self.size(left) - 1);
+            self.nodes[node].right = a;
+            self.update(node);
+            (node, b)
+        }
+    }
+
+    fn merge(&mut self, a: usize, b: usize) -> usize {
+        if a == NIL {
+            return b;
+        }
+        if b == NIL {
+            return a;
+        }
 
-    for _ in 0..times {
-        for original_index in 0..mixed.len() {
-            let mix_from = mixed
-                .iter()
-                .position(|(i, _)| *i == original_index)
-                .unwrap();
+        if self.nodes[a].priority > self.nodes[b].priority {
+            let right = self.nodes[a].right;
+            self.nodes[a].right = self.merge(right, b);
+            self.update(a);
+            a
+        } else {
+            let left = self.nodes[b].left;
+            self.nodes[b].left = self.merge(a, left);
+            self.update(b);
+            b
+        }
+    }
 
-            let elem = mixed.remove(mix_from);
+    fn position_of(&self, original_index: usize) -> usize {
+        let mut node = original_index;
+        let mut position = self.size(self.nodes[node].left);
 
-            let mut mix_to = mix_from as isize + elem.1;
-            mix_to = mix_to.rem_euclid(mixed.len() as isize);
+        while self.nodes[node].parent != NIL {
+            let parent = self.nodes[node].parent;
+            if self.nodes[parent].right == node {
+                position += self.size(self.nodes[parent].left) + 1;
+            }
+            node = parent;
+        }
+
+        position
+    }
 
-            mixed.insert(mix_to as usize, elem);
+    fn get(&self, mut position: usize) -> i64 {
+        let mut node = self.root;
+        loop {
+            let left_size = self.size(self.nodes[node].left);
+            if position < left_size {
+                node = self.nodes[node].left;
+            } else if position == left_size {
+                return self.nodes[node].value;
+            } else {
+                position -= left_size + 1;
+                node = self.nodes[node].right;
+            }
         }
     }
 
-    let zero_pos = mixed.iter().position(|(_, n)| *n == 0).unwrap();
-    let a = (zero_pos + 1000).rem_euclid(mixed.len());
-    let b = (zero_pos + 2000).rem_euclid(mixed.len());
-    let c = (zero_pos + 3000).rem_euclid(mixed.len());
+    fn mix_element(&mut self, original_index: usize) {
+        if self.len() < 2 {
+            return;
+        }
+
+        let from = self.position_of(original_index);
+        let (before, rest) = self.split(self.root, from);
+        let (element, after) = self.split(rest, 1);
+        let remaining = self.merge(before, after);
+        self.detach(remaining);
+
+        let to = (from as i64 + self.nodes[element].value).rem_euclid(self.len() as i64 - 1);
+        let (before, after) = self.split(remaining, to as usize);
+        let joined = self.merge(before, element);
+        self.root = self.merge(joined, after);
+        self.detach(self.root);
+    }
 
-    (mixed[a].1 + mixed[b].1 + mixed[c].1) as usize
+    fn mix(&mut self) {
+        for original_index in 0..self.len() {
+            self.mix_element(original_index);
+        }
+    }
+
+    #[allow(dead_code)]
+    fn sequence(&self) -> Vec<i64> {
+        (0..self.len()).map(|position| self.get(position)).collect()
+    }
+
+    fn grove_coordinates(&self, offsets: &[usize]) -> Vec<i64> {
+        let zero = self.nodes.iter().position(|node| node.value == 0).unwrap();
+        let zero_position = self.position_of(zero);
+
+        offsets
+            .iter()
+            .map(|offset| self.get((zero_position + offset) % self.len()))
+            .collect()
+    }
+}
+
+pub fn part1() -> usize {
+    let numbers: Vec<i64> = parser::read("data/day20.txt").unwrap();
+    sum_after_mixing(&numbers, 1, 1) as usize
+}
+
+pub fn part2() -> usize {
+    let numbers: Vec<i64> = parser::read("data/day20.txt").unwrap();
+    sum_after_mixing(&numbers, 811589153, 10) as usize
+}
+
+fn mixed(numbers: &[i64], decryption_key: i64, rounds: usize) -> MixingList {
+    let decrypted: Vec<i64> = numbers.iter().map(|n| n * decryption_key).collect();
+    let mut list = MixingList::new(&decrypted);
+    for _ in 0..rounds {
+        list.mix();
+    }
+    list
+}
+
+fn sum_after_mixing(numbers: &[i64], decryption_key: i64, rounds: usize) -> i64 {
+    mixed(numbers, decryption_key, rounds)
+        .grove_coordinates(&[1000, 2000, 3000])
+        .iter()
+        .sum()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const SAMPLE: [i64; 7] = [1, 2, -3, 3, -2, 0, 4];
+
+    fn from_zero(sequence: Vec<i64>) -> Vec<i64> {
+        let zero = sequence.iter().position(|&n| n == 0).unwrap();
+        let mut rotated = sequence;
+        rotated.rotate_left(zero);
+        rotated
+    }
+
     #[test]
-    fn sample_input_part_1() {
-        let input = vec!["1", "2", "-3", "3", "-2", "0", "4"];
-        let numbers: Vec<isize> = input.iter().map(|s| s.parse().unwrap()).collect();
+    fn intermediate_states() {
+        let mut list = MixingList::new(&SAMPLE);
+        let expected = [
+            [2, 1, -3, 3, -2, 0, 4],
+            [1, -3, 2, 3, -2, 0, 4],
+            [1, 2, 3, -2, -3, 0, 4],
+            [1, 2, -2, -3, 0, 3, 4],
+            [1, 2, -3, 0, 3, 4, -2],
+            [1, 2, -3, 0, 3, 4, -2],
+            [1, 2, -3, 4, 0, 3, -2],
+        ];
 
-        let result = sum_after_mixing(&numbers, 1, 1);
+        for (original_index, state) in expected.iter().enumerate() {
+            list.mix_element(original_index);
+            assert_eq!(from_zero(list.sequence()), from_zero(state.to_vec()));
+        }
+    }
+
+    #[test]
+    fn rounds_with_decryption_key() {
+        let list = mixed(&SAMPLE, 811589153, 1);
+        assert_eq!(
+            from_zero(list.sequence()),
+            from_zero(vec![
+                0,
+                -2434767459,
+                3246356612,
+                -1623178306,
+                2434767459,
+                1623178306,
+                811589153
+            ])
+        );
 
-        assert_eq!(result, 3);
+        let list = mixed(&SAMPLE, 811589153, 10);
+        assert_eq!(
+            from_zero(list.sequence()),
+            from_zero(vec![
+                0,
+                -2434767459,
+                1623178306,
+                3246356612,
+                -1623178306,
+                2434767459,
+                811589153
+            ])
+        );
     }
 
     #[test]
-    fn sample_input_part_2() {
-        let input = vec!["1", "2", "-3", "3", "-2", "0", "4"];
-        let numbers: Vec<isize> = input.iter().map(|s| s.parse().unwrap()).collect();
+    fn arbitrary_offsets() {
+        let list = mixed(&SAMPLE, 1, 1);
+
+        assert_eq!(list.grove_coordinates(&[1000, 2000, 3000]), vec![4, -3, 2]);
+        assert_eq!(list.grove_coordinates(&[0, 1, 7]), vec![0, 3, 0]);
+    }
 
-        let result = sum_after_mixing(&numbers, 811589153, 10);
+    #[test]
+    fn sample_input_part_1() {
+        assert_eq!(sum_after_mixing(&SAMPLE, 1, 1), 3);
+    }
 
-        assert_eq!(result, 1623178306);
+    #[test]
+    fn sample_input_part_2() {
+        assert_eq!(sum_after_mixing(&SAMPLE, 811589153, 10), 1623178306);
     }
 }
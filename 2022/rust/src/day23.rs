@@ -1,139 +1,224 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use crate::parser;
 use itertools::Itertools;
 
-pub fn part1() -> usize {
-    let lines: Vec<String> = parser::read("data/day23.txt").unwrap();
-    let elves = parse(lines);
-    count_empty_grounds(elves)
+type Position = (isize, isize);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Proposal {
+    direction: Position,
+    checks: Vec<Position>,
 }
 
-pub fn part2() -> usize {
-    let lines: Vec<String> = parser::read("data/day23.txt").unwrap();
-    let elves = parse(lines);
-    first_no_move(elves)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conflict {
+    CancelAll,
+    FirstWins,
 }
 
-fn count_empty_grounds(elves: Vec<(isize, isize)>) -> usize {
-    let mut directions = vec![(0, -1), (0, 1), (-1, 0), (1, 0)];
-    let final_elves = (0..10).fold(elves, |acc, _| {
-        let new_elves = round_move(&acc, &directions);
-        directions.rotate_left(1);
-        new_elves
-    });
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rules {
+    proposals: Vec<Proposal>,
+    neighbourhood: Vec<Position>,
+    conflict: Conflict,
+    rotation_step: usize,
+}
 
-    let xs: Vec<isize> = final_elves.iter().map(|elf| elf.0).collect();
-    let ys: Vec<isize> = final_elves.iter().map(|elf| elf.1).collect();
+impl Default for Rules {
+    fn default() -> Self {
+        let proposal = |direction: Position, checks: [Position; 3]| Proposal {
+            direction,
+            checks: checks.to_vec(),
+        };
+
+        Rules {
+            proposals: vec![
+                proposal((0, -1), [(-1, -1), (0, -1), (1, -1)]),
+                proposal((0, 1), [(-1, 1), (0, 1), (1, 1)]),
+                proposal((-1, 0), [(-1, -1), (-1, 0), (-1, 1)]),
+                proposal((1, 0), [(1, -1), (1, 0), (1, 1)]),
+            ],
+            neighbourhood: (-1..=1)
+                .cartesian_product(-1..=1)
+                .filter(|&offset| offset != (0, 0))
+                .collect(),
+            conflict: Conflict::CancelAll,
+            rotation_step: 1,
+        }
+    }
+}
 
-    let min_x = xs.iter().min().unwrap();
-    let max_x = xs.iter().max().unwrap();
-    let min_y = ys.iter().min().unwrap();
-    let max_y = ys.iter().max().unwrap();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RoundReport {
+    round: usize,
+    moved: usize,
+    bounds: (Position, Position),
+    empty_ground: usize,
+}
 
-    ((1 + max_x - min_x) * (1 + max_y - min_y)) as usize - final_elves.len()
+#[derive(Debug, Clone)]
+struct Automaton {
+    rules: Rules,
+    elves: HashSet<Position>,
+    round: usize,
 }
 
-fn first_no_move(elves: Vec<(isize, isize)>) -> usize {
-    let mut directions = vec![(0, -1), (0, 1), (-1, 0), (1, 0)];
-    let mut count = 0;
-    let mut current_elves = elves;
+impl Automaton {
+    fn new(elves: Vec<Position>, rules: Rules) -> Self {
+        Automaton {
+            rules,
+            elves: elves.into_iter().collect(),
+            round: 0,
+        }
+    }
 
-    loop {
-        count += 1;
+    fn occupied(&self, (x, y): Position, (dx, dy): Position) -> bool {
+        self.elves.contains(&(x + dx, y + dy))
+    }
 
-        let new_elves = round_move(&current_elves, &directions);
-        if current_elves == new_elves {
-            break;
+    fn proposal(&self, elf: Position) -> Option<Position> {
+        if self
+            .rules
+            .neighbourhood
+            .iter()
+            .all(|&offset| !self.occupied(elf, offset))
+        {
+            return None;
         }
 
-        current_elves = new_elves;
-        directions.rotate_left(1);
+        let count = self.rules.proposals.len();
+        let start = self.round * self.rules.rotation_step;
+        (0..count)
+            .map(|i| &self.rules.proposals[(start + i) % count])
+            .find(|proposal| {
+                proposal
+                    .checks
+                    .iter()
+                    .all(|&offset| !self.occupied(elf, offset))
+            })
+            .map(|proposal| (elf.0 + proposal.direction.0, elf.1 + proposal.direction.1))
     }
 
-    count
-}
-
-fn round_move(
-    elves: &Vec<(isize, isize)>,
-    directions: &Vec<(isize, isize)>,
-) -> Vec<(isize, isize)> {
-    let elf_cache: HashSet<(isize, isize)> = elves.iter().cloned().collect();
-
-    let new_elves: Vec<(isize, isize)> = elves
-        .iter()
-        .map(|(elf_x, elf_y)| {
-            let free_directions: Vec<&(isize, isize)> = directions
-                .iter()
-                .filter(|(dir_x, dir_y)| {
-                    if *dir_x != 0 {
-                        !elf_cache.contains(&(*elf_x + dir_x, *elf_y))
-                            && !elf_cache.contains(&(*elf_x + dir_x, *elf_y + 1))
-                            && !elf_cache.contains(&(*elf_x + dir_x, *elf_y - 1))
-                    } else {
-                        !elf_cache.contains(&(*elf_x, *elf_y + dir_y))
-                            && !elf_cache.contains(&(*elf_x + 1, *elf_y + dir_y))
-                            && !elf_cache.contains(&(*elf_x - 1, *elf_y + dir_y))
-                    }
-                })
-                .collect();
-
-            let (dir_x, dir_y): (isize, isize) = if free_directions.len() == 4 {
-                (0, 0)
-            } else {
-                free_directions.first().map(|dir| **dir).unwrap_or((0, 0))
-            };
-
-            (elf_x + dir_x, elf_y + dir_y)
-        })
-        .collect();
-
-    let position_count = new_elves.iter().counts();
-    new_elves
-        .iter()
-        .enumerate()
-        .map(|(i, elf)| {
-            let elves_count = *position_count.get(&elf).unwrap();
-            if elves_count > 1 {
-                elves[i]
-            } else {
-                *elf
+    fn step(&mut self) -> RoundReport {
+        let mut targets: HashMap<Position, Vec<Position>> = HashMap::new();
+        for elf in self.elves.iter().sorted_by_key(|&&(x, y)| (y, x)) {
+            if let Some(target) = self.proposal(*elf) {
+                targets.entry(target).or_default().push(*elf);
             }
-        })
-        .collect()
-}
+        }
 
-#[allow(dead_code)]
-fn print(elves: &Vec<(isize, isize)>) {
-    let xs: Vec<isize> = elves.iter().map(|elf| elf.0).collect();
-    let ys: Vec<isize> = elves.iter().map(|elf| elf.1).collect();
-
-    let min_x = xs.iter().min().unwrap();
-    let max_x = xs.iter().max().unwrap();
-    let min_y = ys.iter().min().unwrap();
-    let max_y = ys.iter().max().unwrap();
-
-    let width = (max_x - min_x + 1) as usize;
-    let height = (max_y - min_y + 1) as usize;
-
-    let adjusted_elves: Vec<(usize, usize)> = elves
-        .iter()
-        .map(|elf| ((elf.0 - min_x) as usize, (elf.1 - min_y) as usize))
-        .collect();
-
-    for y in 0..height {
-        for x in 0..width {
-            if adjusted_elves.contains(&(x, y)) {
-                print!("#");
-            } else {
-                print!(".");
+        let moves: Vec<(Position, Position)> = targets
+            .into_iter()
+            .filter(|(_, from)| self.rules.conflict == Conflict::FirstWins || from.len() == 1)
+            .map(|(to, from)| (from[0], to))
+            .collect();
+
+        for (from, _) in &moves {
+            self.elves.remove(from);
+        }
+        for (_, to) in &moves {
+            self.elves.insert(*to);
+        }
+        self.round += 1;
+
+        RoundReport {
+            round: self.round,
+            moved: moves.len(),
+            bounds: self.bounds(),
+            empty_ground: self.empty_ground(),
+        }
+    }
+
+    fn run_until_stable(&mut self, max_rounds: usize) -> Option<usize> {
+        while self.round < max_rounds {
+            let report = self.step();
+            if report.moved == 0 {
+                return Some(report.round);
             }
         }
-        println!();
+        None
+    }
+
+    fn bounds(&self) -> (Position, Position) {
+        let (min_x, max_x) = self
+            .elves
+            .iter()
+            .map(|elf| elf.0)
+            .minmax()
+            .into_option()
+            .unwrap_or((0, 0));
+        let (min_y, max_y) = self
+            .elves
+            .iter()
+            .map(|elf| elf.1)
+            .minmax()
+            .into_option()
+            .unwrap_or((0, 0));
+        ((min_x, min_y), (max_x, max_y))
     }
+
+    fn empty_ground(&self) -> usize {
+        let ((min_x, min_y), (max_x, max_y)) = self.bounds();
+        ((1 + max_x - min_x) * (1 + max_y - min_y)) as usize - self.elves.len()
+    }
+
+    #[allow(dead_code)]
+    fn positions(&self) -> Vec<Position> {
+        self.elves
+            .iter()
+            .copied()
+            .sorted_by_key(|&(x, y)| (y, x))
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    fn render(&self) -> String {
+        let ((min_x, min_y), (max_x, max_y)) = self.bounds();
+
+        (min_y..=max_y)
+            .map(|y| {
+                (min_x..=max_x)
+                    .map(|x| {
+                        if self.elves.contains(&(x, y)) {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .join("\n")
+    }
+}
+
+pub fn part1() -> usize {
+    let lines: Vec<String> = parser::read("data/day23.txt").unwrap();
+    let elves = parse(lines);
+    count_empty_grounds(elves)
+}
+
+pub fn part2() -> usize {
+    let lines: Vec<String> = parser::read("data/day23.txt").unwrap();
+    let elves = parse(lines);
+    first_no_move(elves)
+}
+
+fn count_empty_grounds(elves: Vec<Position>) -> usize {
+    let mut automaton = Automaton::new(elves, Rules::default());
+    (0..10)
+        .map(|_| automaton.step())
+        .last()
+        .map(|report| report.empty_ground)
+        .unwrap()
+}
+
+fn first_no_move(elves: Vec<Position>) -> usize {
+    let mut automaton = Automaton::new(elves, Rules::default());
+    automaton.run_until_stable(usize::MAX).unwrap()
 }
 
-fn parse(lines: Vec<String>) -> Vec<(isize, isize)> {
+fn parse(lines: Vec<String>) -> Vec<Position> {
     let mut elves = Vec::new();
 
     for (y, row) in lines.iter().enumerate() {
@@ -151,6 +236,47 @@ fn parse(lines: Vec<String>) -> Vec<(isize, isize)> {
 mod tests {
     use super::*;
 
+    fn small_example() -> Vec<Position> {
+        let input = [".....", "..##.", "..#..", ".....", "..##.", "....."];
+        parse(input.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn small_example_rounds() {
+        let mut automaton = Automaton::new(small_example(), Rules::default());
+
+        let report = automaton.step();
+        assert_eq!(report.moved, 3);
+        assert_eq!(report.bounds, ((2, 0), (3, 4)));
+        assert_eq!(report.empty_ground, 5);
+        assert_eq!(automaton.render(), "##\n..\n#.\n.#\n#.");
+
+        automaton.step();
+        automaton.step();
+        assert_eq!(
+            automaton.render(),
+            ["..#..", "....#", "#....", "....#", ".....", "..#.."].join("\n")
+        );
+        assert_eq!(
+            automaton.positions(),
+            vec![(2, 0), (4, 1), (0, 2), (4, 3), (2, 5)]
+        );
+        assert_eq!(automaton.run_until_stable(10), Some(4));
+    }
+
+    #[test]
+    fn first_proposal_wins() {
+        let rules = Rules {
+            conflict: Conflict::FirstWins,
+            ..Rules::default()
+        };
+        let mut automaton = Automaton::new(small_example(), rules);
+
+        assert_eq!(automaton.step().moved, 4);
+        assert!(automaton.elves.contains(&(2, 3)));
+        assert!(automaton.elves.contains(&(2, 4)));
+    }
+
     #[test]
     fn sample_input_part_1() {
         let input = vec![
@@ -2,123 +2,324 @@ use std::str::FromStr;
 
 use crate::parser;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Move {
     quantity: usize,
     from: usize,
     to: usize,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 struct ParseMoveError;
 
 impl FromStr for Move {
     type Err = ParseMoveError;
 
     fn from_str(s: &str) -> Result<Self, ParseMoveError> {
-        let mut words = s.split_ascii_whitespace();
+        let words: Vec<&str> = s.split_ascii_whitespace().collect();
+        let number = |word: &str| word.parse::<usize>().map_err(|_| ParseMoveError);
 
-        words.next();
-        let quantity: usize = words.next().unwrap().parse().unwrap();
+        match words[..] {
+            ["move", quantity, "from", from, "to", to] => {
+                let (from, to) = (number(from)?, number(to)?);
+                if from == 0 || to == 0 {
+                    return Err(ParseMoveError);
+                }
+                Ok(Move {
+                    quantity: number(quantity)?,
+                    from,
+                    to,
+                })
+            }
+            _ => Err(ParseMoveError),
+        }
+    }
+}
 
-        words.next();
-        let from: usize = words.next().unwrap().parse().unwrap();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Crane {
+    // Moves crates one at a time, reversing their order
+    CrateMover9000,
+    // Moves several crates at once, keeping their order
+    CrateMover9001,
+}
 
-        words.next();
-        let to: usize = words.next().unwrap().parse().unwrap();
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MoveError {
+    UnknownStack {
+        step: usize,
+        stack: usize,
+    },
+    NotEnoughCrates {
+        step: usize,
+        stack: usize,
+        requested: usize,
+        available: usize,
+    },
+}
 
-        Ok(Move { quantity, from, to})
-    }
+// Each stack is stored from the bottom crate to the top one
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Stacks {
+    stacks: Vec<Vec<char>>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+struct ParseDrawingError;
+
+impl FromStr for Stacks {
+    type Err = ParseDrawingError;
+
+    fn from_str(s: &str) -> Result<Self, ParseDrawingError> {
+        let mut rows = s.lines().rev();
+        let labels: Vec<&str> = rows
+            .next()
+            .ok_or(ParseDrawingError)?
+            .split_ascii_whitespace()
+            .collect();
+        for (i, label) in labels.iter().enumerate() {
+            if label.parse::<usize>() != Ok(i + 1) {
+                return Err(ParseDrawingError);
+            }
+        }
+
+        let mut stacks: Vec<Vec<char>> = vec![vec![]; labels.len()];
+        for (level, row) in rows.enumerate() {
+            let cells: Vec<char> = row.chars().collect();
+            if cells.len() > 4 * stacks.len() {
+                return Err(ParseDrawingError);
+            }
+
+            for (i, cell) in cells.chunks(4).enumerate() {
+                match cell {
+                    ['[', name, ']', ..] if name.is_ascii_alphabetic() => {
+                        // A crate cannot float above an empty spot
+                        if stacks[i].len() != level {
+                            return Err(ParseDrawingError);
+                        }
+                        stacks[i].push(*name);
+                    }
+                    [' ', ' ', ' ', ..] | [' ', ' '] | [' '] => {}
+                    _ => return Err(ParseDrawingError),
+                }
+            }
+        }
 
-pub fn part1() -> usize {
-    let mut inital_crates = vec![
-        vec!['R', 'G', 'J', 'B', 'T', 'V', 'Z'],
-        vec!['J', 'R', 'V', 'L'],
-        vec!['S', 'Q', 'F'],
-        vec!['Z', 'H', 'N', 'L', 'F', 'V', 'Q', 'G'],
-        vec!['R', 'Q', 'T', 'J', 'C', 'S', 'M', 'W'],
-        vec!['S', 'W', 'T', 'C', 'H', 'F'],
-        vec!['D', 'Z', 'C', 'V', 'F', 'N', 'J'],
-        vec!['L', 'G', 'Z', 'D', 'W', 'R', 'F', 'Q'],
-        vec!['J', 'B', 'W', 'V', 'P'],
-    ];
-    let moves: Vec<Move> = parser::read("data/day5.txt").unwrap();
-    let crate_word = top_stack(&mut inital_crates, &moves);
-    println!("{}", crate_word);
-    0
+        Ok(Stacks { stacks })
+    }
 }
 
-pub fn part2() -> usize {
-    let mut inital_crates = vec![
-        vec!['R', 'G', 'J', 'B', 'T', 'V', 'Z'],
-        vec!['J', 'R', 'V', 'L'],
-        vec!['S', 'Q', 'F'],
-        vec!['Z', 'H', 'N', 'L', 'F', 'V', 'Q', 'G'],
-        vec!['R', 'Q', 'T', 'J', 'C', 'S', 'M', 'W'],
-        vec!['S', 'W', 'T', 'C', 'H', 'F'],
-        vec!['D', 'Z', 'C', 'V', 'F', 'N', 'J'],
-        vec!['L', 'G', 'Z', 'D', 'W', 'R', 'F', 'Q'],
-        vec!['J', 'B', 'W', 'V', 'P'],
-    ];
-    let moves: Vec<Move> = parser::read("data/day5.txt").unwrap();
-    let crate_word = top_stack_9001(&mut inital_crates, &moves);
-    println!("{}", crate_word);
-    0
+fn row_height(stacks: &[Vec<char>]) -> usize {
+    stacks.iter().map(Vec::len).max().unwrap_or(0)
 }
 
-fn top_stack(crates: &mut Vec<Vec<char>>, moves: &Vec<Move>) -> String {
-    for create_move in moves {
-        for _ in 0..create_move.quantity {
-            let element = crates[create_move.from - 1].pop().unwrap();
-            crates[create_move.to - 1].push(element);
+impl Stacks {
+    fn apply(&mut self, crane: Crane, step: usize, crate_move: &Move) -> Result<(), MoveError> {
+        for stack in [crate_move.from, crate_move.to] {
+            if stack > self.stacks.len() {
+                return Err(MoveError::UnknownStack { step, stack });
+            }
+        }
+
+        let from = &mut self.stacks[crate_move.from - 1];
+        if from.len() < crate_move.quantity {
+            return Err(MoveError::NotEnoughCrates {
+                step,
+                stack: crate_move.from,
+                requested: crate_move.quantity,
+                available: from.len(),
+            });
         }
+
+        let mut lifted = from.split_off(from.len() - crate_move.quantity);
+        if crane == Crane::CrateMover9000 {
+            lifted.reverse();
+        }
+        self.stacks[crate_move.to - 1].append(&mut lifted);
+        Ok(())
+    }
+
+    fn top(&self) -> String {
+        self.stacks
+            .iter()
+            .filter_map(|stack| stack.last())
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    fn render(&self) -> String {
+        let mut lines: Vec<String> = (0..row_height(&self.stacks))
+            .rev()
+            .map(|level| {
+                self.stacks
+                    .iter()
+                    .map(|stack| match stack.get(level) {
+                        Some(name) => format!("[{}]", name),
+                        None => "   ".to_string(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ")
+                    .trim_end()
+                    .to_string()
+            })
+            .collect();
+
+        let labels: Vec<String> = (1..=self.stacks.len())
+            .map(|label| format!(" {} ", label))
+            .collect();
+        lines.push(labels.join(" ").trim_end().to_string());
+        lines.join("\n")
     }
-    crates.into_iter().map(|c| c.pop().unwrap()).collect()
 }
 
-fn top_stack_9001(crates: &mut Vec<Vec<char>>, moves: &Vec<Move>) -> String {
-    for create_move in moves {
-        let split_index = crates[create_move.from - 1].len() - create_move.quantity as usize;
-        let mut elements = crates[create_move.from - 1].split_off(split_index);
-        crates[create_move.to - 1].append(&mut elements);
+pub fn part1() -> String {
+    let lines: Vec<String> = parser::read("data/day5.txt").unwrap();
+    let (stacks, moves) = parse(&lines);
+    top_crates(&stacks, Crane::CrateMover9000, &moves).unwrap()
+}
+
+pub fn part2() -> String {
+    let lines: Vec<String> = parser::read("data/day5.txt").unwrap();
+    let (stacks, moves) = parse(&lines);
+    top_crates(&stacks, Crane::CrateMover9001, &moves).unwrap()
+}
+
+fn parse(lines: &[String]) -> (Stacks, Vec<Move>) {
+    let separator = lines.iter().position(|line| line.is_empty()).unwrap();
+    let stacks = lines[..separator].join("\n").parse().unwrap();
+    let moves = lines[separator + 1..]
+        .iter()
+        .map(|line| line.parse().unwrap())
+        .collect();
+    (stacks, moves)
+}
+
+// Returns the stacks before the first move and after every following one
+#[allow(dead_code)]
+fn replay(stacks: &Stacks, crane: Crane, moves: &[Move]) -> Result<Vec<Stacks>, MoveError> {
+    let mut states = vec![stacks.clone()];
+    for (step, crate_move) in moves.iter().enumerate() {
+        let mut next = states[step].clone();
+        next.apply(crane, step + 1, crate_move)?;
+        states.push(next);
     }
-    crates.into_iter().map(|c| c.pop().unwrap()).collect()
+    Ok(states)
+}
+
+fn top_crates(stacks: &Stacks, crane: Crane, moves: &[Move]) -> Result<String, MoveError> {
+    let mut stacks = stacks.clone();
+    for (step, crate_move) in moves.iter().enumerate() {
+        stacks.apply(crane, step + 1, crate_move)?;
+    }
+    Ok(stacks.top())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn sample_input() -> (Stacks, Vec<Move>) {
+        let lines: Vec<String> = [
+            "    [D]    ",
+            "[N] [C]    ",
+            "[Z] [M] [P]",
+            " 1   2   3 ",
+            "",
+            "move 1 from 2 to 1",
+            "move 3 from 1 to 3",
+            "move 2 from 2 to 1",
+            "move 1 from 1 to 2",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        parse(&lines)
+    }
+
+    #[test]
+    fn parsing() {
+        let (stacks, moves) = sample_input();
+
+        assert_eq!(
+            stacks.stacks,
+            vec![vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P']]
+        );
+        assert_eq!(
+            moves[1],
+            Move {
+                quantity: 3,
+                from: 1,
+                to: 3
+            }
+        );
+        assert_eq!("move 1 from x to 2".parse::<Move>(), Err(ParseMoveError));
+        assert_eq!("move 1 from 0 to 2".parse::<Move>(), Err(ParseMoveError));
+        assert_eq!("[A] [B]\n 1 ".parse::<Stacks>(), Err(ParseDrawingError));
+        assert_eq!(
+            "[A]\n    [B]\n 1   2".parse::<Stacks>(),
+            Err(ParseDrawingError)
+        );
+    }
+
+    #[test]
+    fn rendering_steps() {
+        let (stacks, moves) = sample_input();
+        let states = replay(&stacks, Crane::CrateMover9000, &moves).unwrap();
+
+        assert_eq!(
+            states[0].render(),
+            "    [D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3"
+        );
+        assert_eq!(
+            states[2].render(),
+            [
+                "        [Z]",
+                "        [N]",
+                "    [C] [D]",
+                "    [M] [P]",
+                " 1   2   3"
+            ]
+            .join("\n")
+        );
+        assert_eq!(states[4].top(), "CMZ");
+    }
+
+    #[test]
+    fn empty_stack() {
+        let (stacks, _) = sample_input();
+        let moves = vec!["move 2 from 3 to 1".parse().unwrap()];
+
+        assert_eq!(
+            top_crates(&stacks, Crane::CrateMover9001, &moves),
+            Err(MoveError::NotEnoughCrates {
+                step: 1,
+                stack: 3,
+                requested: 2,
+                available: 1
+            })
+        );
+        let moves = vec!["move 1 from 4 to 1".parse().unwrap()];
+        assert_eq!(
+            top_crates(&stacks, Crane::CrateMover9000, &moves),
+            Err(MoveError::UnknownStack { step: 1, stack: 4 })
+        );
+    }
+
     #[test]
     fn sample_input_top_stack() {
-        let mut inital_crates = vec![
-            vec!['Z', 'N'],
-            vec!['M', 'C', 'D'],
-            vec!['P'],
-        ];
-        let moves = vec![
-            Move { quantity: 1, from: 2, to: 1 },
-            Move { quantity: 3, from: 1, to: 3 },
-            Move { quantity: 2, from: 2, to: 1 },
-            Move { quantity: 1, from: 1, to: 2 },
-        ];
-        assert_eq!(top_stack(&mut inital_crates, &moves), "CMZ");
+        let (stacks, moves) = sample_input();
+        assert_eq!(
+            top_crates(&stacks, Crane::CrateMover9000, &moves),
+            Ok("CMZ".to_string())
+        );
     }
 
     #[test]
     fn sample_input_top_stack_9001() {
-        let mut inital_crates = vec![
-            vec!['Z', 'N'],
-            vec!['M', 'C', 'D'],
-            vec!['P'],
-        ];
-        let moves = vec![
-            Move { quantity: 1, from: 2, to: 1 },
-            Move { quantity: 3, from: 1, to: 3 },
-            Move { quantity: 2, from: 2, to: 1 },
-            Move { quantity: 1, from: 1, to: 2 },
-        ];
-        assert_eq!(top_stack_9001(&mut inital_crates, &moves), "MCD");
+        let (stacks, moves) = sample_input();
+        assert_eq!(
+            top_crates(&stacks, Crane::CrateMover9001, &moves),
+            Ok("MCD".to_string())
+        );
     }
 }
@@ -1,54 +1,134 @@
-
-use itertools::Itertools;
+use std::{
+    collections::VecDeque,
+    io::{self, BufReader, Bytes, Read},
+};
 
 use crate::parser;
 
+const PACKET_MARKER_SIZE: usize = 4;
+const MESSAGE_MARKER_SIZE: usize = 14;
+
+// Yields the position right after every window of distinct bytes in the stream,
+// keeping a count per byte so each step only looks at the bytes entering and leaving
+struct MarkerDetector<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+    window_size: usize,
+    window: VecDeque<u8>,
+    counts: [usize; 256],
+    duplicates: usize,
+    position: usize,
+}
+
+impl<R: Read> MarkerDetector<R> {
+    fn new(stream: R, window_size: usize) -> Self {
+        MarkerDetector {
+            bytes: BufReader::new(stream).bytes(),
+            window_size,
+            window: VecDeque::with_capacity(window_size + 1),
+            counts: [0; 256],
+            duplicates: 0,
+            position: 0,
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.window.push_back(byte);
+        self.counts[byte as usize] += 1;
+        if self.counts[byte as usize] == 2 {
+            self.duplicates += 1;
+        }
+
+        if self.window.len() > self.window_size {
+            let left = self.window.pop_front().unwrap();
+            self.counts[left as usize] -= 1;
+            if self.counts[left as usize] == 1 {
+                self.duplicates -= 1;
+            }
+        }
+        self.position += 1;
+    }
+}
+
+impl<R: Read> Iterator for MarkerDetector<R> {
+    type Item = io::Result<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.bytes.next()? {
+                Err(error) => return Some(Err(error)),
+                Ok(byte) => {
+                    self.push(byte);
+                    if self.window.len() == self.window_size && self.duplicates == 0 {
+                        return Some(Ok(self.position));
+                    }
+                }
+            }
+        }
+    }
+}
+
 pub fn part1() -> usize {
     let signal: Vec<String> = parser::read("data/day6.txt").unwrap();
-    marker_index(signal.first().unwrap())
+    first_marker(signal.first().unwrap(), PACKET_MARKER_SIZE).unwrap()
 }
 
 pub fn part2() -> usize {
     let signal: Vec<String> = parser::read("data/day6.txt").unwrap();
-    start_of_message(signal.first().unwrap())
+    first_marker(signal.first().unwrap(), MESSAGE_MARKER_SIZE).unwrap()
 }
 
-fn marker_index(signal: &str) -> usize {
-    let potential_markers: Vec<char> = signal.chars().collect();
-    let marker = potential_markers
-        .windows(4)
-        .enumerate()
-        .find(|(_, marker)| marker.into_iter().all_unique());
-    (marker.unwrap().0 + 4) as usize
+fn first_marker(signal: &str, window_size: usize) -> Option<usize> {
+    MarkerDetector::new(signal.as_bytes(), window_size)
+        .next()
+        .map(Result::unwrap)
 }
 
-fn start_of_message(signal: &str) -> usize {
-    let potential_markers: Vec<char> = signal.chars().collect();
-    let marker = potential_markers
-        .windows(14)
-        .enumerate()
-        .find(|(_, marker)| marker.into_iter().all_unique());
-    (marker.unwrap().0 + 14) as usize
+#[allow(dead_code)]
+fn all_markers<R: Read>(stream: R, window_size: usize) -> io::Result<Vec<usize>> {
+    MarkerDetector::new(stream, window_size).collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn every_marker_position() {
+        assert_eq!(
+            all_markers("abcabd".as_bytes(), 3).unwrap(),
+            vec![3, 4, 5, 6]
+        );
+        assert_eq!(all_markers("aabbaa".as_bytes(), 2).unwrap(), vec![3, 5]);
+        assert!(all_markers("aaaa".as_bytes(), 2).unwrap().is_empty());
+        assert_eq!(first_marker("abc", 4), None);
+    }
+
     #[test]
     fn sample_input_marker_index() {
-        assert_eq!(marker_index("bvwbjplbgvbhsrlpgdmjqwftvncz"), 5);
-        assert_eq!(marker_index("nppdvjthqldpwncqszvftbrmjlhg"), 6);
-        assert_eq!(marker_index("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"), 10);
-        assert_eq!(marker_index("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"), 11);
+        assert_eq!(first_marker("bvwbjplbgvbhsrlpgdmjqwftvncz", 4), Some(5));
+        assert_eq!(first_marker("nppdvjthqldpwncqszvftbrmjlhg", 4), Some(6));
+        assert_eq!(
+            first_marker("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 4),
+            Some(10)
+        );
+        assert_eq!(
+            first_marker("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 4),
+            Some(11)
+        );
     }
 
     #[test]
     fn sample_input_start_of_message() {
-        assert_eq!(start_of_message("mjqjpqmgbljsphdztnvjfqwrcgsmlb"), 19);
-        assert_eq!(start_of_message("bvwbjplbgvbhsrlpgdmjqwftvncz"), 23);
-        assert_eq!(start_of_message("nppdvjthqldpwncqszvftbrmjlhg"), 23);
-        assert_eq!(start_of_message("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"), 29);
-        assert_eq!(start_of_message("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"), 26);
+        assert_eq!(first_marker("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 14), Some(19));
+        assert_eq!(first_marker("bvwbjplbgvbhsrlpgdmjqwftvncz", 14), Some(23));
+        assert_eq!(first_marker("nppdvjthqldpwncqszvftbrmjlhg", 14), Some(23));
+        assert_eq!(
+            first_marker("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 14),
+            Some(29)
+        );
+        assert_eq!(
+            first_marker("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 14),
+            Some(26)
+        );
     }
 }
@@ -1,7 +1,88 @@
-use std::iter::repeat;
-
 use crate::parser;
 
+const LEFT: usize = 0;
+const RIGHT: usize = 1;
+const UP: usize = 2;
+const DOWN: usize = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+struct TreeReport {
+    visible_from: Vec<Vec<[bool; 4]>>,
+    viewing_distances: Vec<Vec<[usize; 4]>>,
+}
+
+impl TreeReport {
+    fn new(tree_heights: &[Vec<usize>]) -> Self {
+        let height = tree_heights.len();
+        let width = tree_heights.first().map_or(0, |row| row.len());
+        let mut report = TreeReport {
+            visible_from: vec![vec![[false; 4]; width]; height],
+            viewing_distances: vec![vec![[0; 4]; width]; height],
+        };
+
+        for y in 0..height {
+            report.sweep(tree_heights, LEFT, (0..width).map(|x| (x, y)));
+            report.sweep(tree_heights, RIGHT, (0..width).rev().map(|x| (x, y)));
+        }
+        for x in 0..width {
+            report.sweep(tree_heights, UP, (0..height).map(|y| (x, y)));
+            report.sweep(tree_heights, DOWN, (0..height).rev().map(|y| (x, y)));
+        }
+
+        report
+    }
+
+    // Walks a line starting from the edge on `side`, keeping a stack of trees that can
+    // still block the view, from tallest at the bottom to the closest at the top
+    fn sweep(
+        &mut self,
+        tree_heights: &[Vec<usize>],
+        side: usize,
+        line: impl Iterator<Item = (usize, usize)>,
+    ) {
+        let mut blocking: Vec<(usize, usize)> = vec![];
+
+        for (i, (x, y)) in line.enumerate() {
+            let tree_height = tree_heights[y][x];
+            while blocking
+                .last()
+                .is_some_and(|&(_, blocking_height)| blocking_height < tree_height)
+            {
+                blocking.pop();
+            }
+
+            self.visible_from[y][x][side] = blocking.is_empty();
+            self.viewing_distances[y][x][side] = match blocking.last() {
+                Some(&(blocking_index, _)) => i - blocking_index,
+                None => i,
+            };
+            blocking.push((i, tree_height));
+        }
+    }
+
+    fn is_visible(&self, (x, y): (usize, usize)) -> bool {
+        self.visible_from[y][x].iter().any(|&visible| visible)
+    }
+
+    fn visible_trees(&self) -> Vec<(usize, usize)> {
+        (0..self.visible_from.len())
+            .flat_map(|y| (0..self.visible_from[y].len()).map(move |x| (x, y)))
+            .filter(|&position| self.is_visible(position))
+            .collect()
+    }
+
+    fn scenic_scores(&self) -> Vec<Vec<usize>> {
+        self.viewing_distances
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|distances| distances.iter().product())
+                    .collect()
+            })
+            .collect()
+    }
+}
+
 pub fn part1() -> usize {
     let lines: Vec<String> = parser::read("data/day8.txt").unwrap();
     let tree_heights: Vec<Vec<usize>> = to_height_map(&lines);
@@ -14,95 +95,59 @@ pub fn part2() -> usize {
     highest_scenic_score(&tree_heights)
 }
 
-fn to_height_map(lines: &Vec<String>) -> Vec<Vec<usize>> {
+fn to_height_map(lines: &[String]) -> Vec<Vec<usize>> {
     lines
         .iter()
-        .map(|line| line.chars().map(|c| c as usize).collect())
+        .map(|line| {
+            line.chars()
+                .map(|c| c.to_digit(10).unwrap() as usize)
+                .collect()
+        })
         .collect()
 }
 
-fn trees_visible(tree_heights: &Vec<Vec<usize>>) -> usize {
-    let width = tree_heights[0].len();
-    let height = tree_heights.len();
-    let mut visible_trees = 0;
-
-    for y in 0..height {
-        for x in 0..width {
-            let tree_height = tree_heights[y][x];
-            if is_highest_tree_in_dir(tree_heights, (0..x).zip(repeat(y)), tree_height)
-                || is_highest_tree_in_dir(tree_heights, (x + 1..width).zip(repeat(y)), tree_height)
-                || is_highest_tree_in_dir(tree_heights, repeat(x).zip(0..y), tree_height)
-                || is_highest_tree_in_dir(tree_heights, repeat(x).zip(y + 1..height), tree_height)
-            {
-                visible_trees += 1;
-            }
-        }
-    }
-
-    visible_trees
+fn trees_visible(tree_heights: &[Vec<usize>]) -> usize {
+    TreeReport::new(tree_heights).visible_trees().len()
 }
 
-fn is_highest_tree_in_dir<I>(
-    tree_heights: &Vec<Vec<usize>>,
-    tree_coords_in_direction: I,
-    current_height: usize,
-) -> bool
-where
-    I: Iterator<Item = (usize, usize)>,
-{
-    tree_coords_in_direction
-        .map(|(x, y)| tree_heights[y][x])
-        .all(|tree_height| tree_height < current_height)
+fn highest_scenic_score(tree_heights: &[Vec<usize>]) -> usize {
+    TreeReport::new(tree_heights)
+        .scenic_scores()
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or(0)
 }
 
-fn highest_scenic_score(tree_heights: &Vec<Vec<usize>>) -> usize {
-    let width = tree_heights[0].len();
-    let height = tree_heights.len();
-    let mut max_scenic_score = 0;
-
-    for y in 0..height {
-        for x in 0..width {
-            let tree_height = tree_heights[y][x];
-            let left = scenic_score_in_dir(tree_heights, (0..x).rev().zip(repeat(y)), tree_height, x);
-            let right = scenic_score_in_dir(tree_heights, (x + 1..width).zip(repeat(y)), tree_height, width-x-1);
-            let up = scenic_score_in_dir(tree_heights, repeat(x).zip((0..y).rev()), tree_height, y);
-            let down = scenic_score_in_dir(tree_heights, repeat(x).zip(y + 1..height), tree_height, height-y-1);
-
-            let scenic_score = left * right * up * down;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            if scenic_score > max_scenic_score {
-                max_scenic_score = scenic_score;
-            }
-        }
+    fn sample_heights() -> Vec<Vec<usize>> {
+        let lines: Vec<String> = ["30373", "25512", "65332", "33549", "35390"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        to_height_map(&lines)
     }
 
-    max_scenic_score as usize
-}
-
-fn scenic_score_in_dir<I>(
-    tree_heights: &Vec<Vec<usize>>,
-    tree_coords_in_direction: I,
-    current_height: usize,
-    default: usize
-) -> usize
-where
-    I: Iterator<Item = (usize, usize)>,
-{
-    let first_blocking_tree = tree_coords_in_direction
-        .enumerate()
-        .map(|(i, (x, y))| (i, tree_heights[y][x]))
-        .find(|(_, tree_height)| tree_height >= &current_height)
-        .map(|(i, _)| i + 1);
-
-    match first_blocking_tree {
-        Some(i) => i,
-        None => default,
+    #[test]
+    fn parsing() {
+        assert_eq!(sample_heights()[0], vec![3, 0, 3, 7, 3]);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn per_tree_report() {
+        let report = TreeReport::new(&sample_heights());
+
+        assert_eq!(report.visible_from[1][1], [true, false, true, false]);
+        assert_eq!(report.visible_from[2][2], [false, false, false, false]);
+        assert_eq!(report.viewing_distances[1][2], [1, 2, 1, 2]);
+        assert_eq!(report.viewing_distances[3][2], [2, 2, 2, 1]);
+        assert_eq!(report.scenic_scores()[3][2], 8);
+        assert_eq!(report.scenic_scores()[0], vec![0, 0, 0, 0, 0]);
+        assert!(!report.visible_trees().contains(&(2, 2)));
+    }
 
     #[test]
     fn sample_input_part_1() {
@@ -8,8 +8,14 @@ enum Move {
     Left(i32),
     Up(i32),
     Down(i32),
+    UpRight(i32),
+    UpLeft(i32),
+    DownRight(i32),
+    DownLeft(i32),
 }
 
+type Position = (i32, i32);
+
 #[derive(Debug, Clone)]
 struct ParseMoveError;
 
@@ -26,64 +32,149 @@ impl FromStr for Move {
             "L" => Ok(Move::Left(distance)),
             "U" => Ok(Move::Up(distance)),
             "D" => Ok(Move::Down(distance)),
+            "UR" => Ok(Move::UpRight(distance)),
+            "UL" => Ok(Move::UpLeft(distance)),
+            "DR" => Ok(Move::DownRight(distance)),
+            "DL" => Ok(Move::DownLeft(distance)),
             _ => Err(ParseMoveError),
         }
     }
 }
 
-pub fn part1() -> usize {
-    let moves: Vec<Move> = parser::read("data/day9.txt").unwrap();
-    visited_positions(&moves, 1)
+impl Move {
+    fn direction(&self) -> (Position, i32) {
+        match self {
+            Move::Right(distance) => ((1, 0), *distance),
+            Move::Left(distance) => ((-1, 0), *distance),
+            Move::Up(distance) => ((0, 1), *distance),
+            Move::Down(distance) => ((0, -1), *distance),
+            Move::UpRight(distance) => ((1, 1), *distance),
+            Move::UpLeft(distance) => ((-1, 1), *distance),
+            Move::DownRight(distance) => ((1, -1), *distance),
+            Move::DownLeft(distance) => ((-1, -1), *distance),
+        }
+    }
 }
 
-pub fn part2() -> usize {
-    let moves: Vec<Move> = parser::read("data/day9.txt").unwrap();
-    visited_positions(&moves, 9)
+#[derive(Debug, Clone)]
+struct Rope {
+    history: Vec<Vec<Position>>,
+    // One rendered frame per move, once tracing is on
+    trace: Option<Vec<String>>,
 }
 
-fn visited_positions(moves: &Vec<Move>, rope_length: usize) -> usize {
-    let mut rope: Vec<(i32, i32)> = vec![(0, 0)];
-    for _ in 0..rope_length {
-        rope.push((0, 0));
+impl Rope {
+    fn new(knots: usize) -> Self {
+        Rope {
+            history: vec![vec![(0, 0); knots]],
+            trace: None,
+        }
     }
 
-    let mut visited = HashSet::new();
-    visited.insert(rope.last().unwrap().clone());
+    #[allow(dead_code)]
+    fn with_trace(self) -> Self {
+        Rope {
+            trace: Some(vec![]),
+            ..self
+        }
+    }
 
-    for rope_move in moves {
-        println!("=== Move {:?} ===", rope_move);
-        let ((x, y), steps) = match rope_move {
-            Move::Right(distance) => ((1, 0), *distance),
-            Move::Left(distance) => ((-1, 0), *distance),
-            Move::Up(distance) => ((0, 1), *distance),
-            Move::Down(distance) => ((0, -1), *distance),
-        };
+    #[allow(dead_code)]
+    fn trace(&self) -> &[String] {
+        self.trace.as_deref().unwrap_or_default()
+    }
+
+    fn knots(&self) -> &[Position] {
+        self.history.last().unwrap()
+    }
+
+    fn steps(&self) -> usize {
+        self.history.len() - 1
+    }
+
+    fn positions_at(&self, step: usize) -> &[Position] {
+        &self.history[step]
+    }
+
+    fn apply(&mut self, rope_move: &Move) {
+        let ((x, y), steps) = rope_move.direction();
 
         for _ in 0..steps {
+            let mut rope = self.knots().to_vec();
             rope[0].0 += x;
             rope[0].1 += y;
 
             for i in 1..rope.len() {
-                if (rope[i - 1].0 - rope[i].0) > 1 {
-                    rope[i].0 += 1;
-                    rope[i].1 -= (rope[i].1 - rope[i - 1].1).clamp(-1, 1);
-                } else if (rope[i - 1].0 - rope[i].0) < -1 {
-                    rope[i].0 -= 1;
-                    rope[i].1 -= (rope[i].1 - rope[i - 1].1).clamp(-1, 1);
-                } else if (rope[i - 1].1 - rope[i].1) > 1 {
-                    rope[i].0 -= (rope[i].0 - rope[i - 1].0).clamp(-1, 1);
-                    rope[i].1 += 1;
-                } else if (rope[i - 1].1 - rope[i].1) < -1 {
-                    rope[i].0 -= (rope[i].0 - rope[i - 1].0).clamp(-1, 1);
-                    rope[i].1 -= 1;
+                let dx = rope[i - 1].0 - rope[i].0;
+                let dy = rope[i - 1].1 - rope[i].1;
+                if dx.abs() > 1 || dy.abs() > 1 {
+                    rope[i].0 += dx.signum();
+                    rope[i].1 += dy.signum();
                 }
             }
 
-            visited.insert(rope.last().unwrap().clone());
+            self.history.push(rope);
         }
+
+        if let Some(mut trace) = self.trace.take() {
+            trace.push(format!(
+                "== {:?} ==\n{}",
+                rope_move,
+                self.render(self.steps())
+            ));
+            self.trace = Some(trace);
+        }
+    }
+
+    fn visited(&self, knot: usize) -> HashSet<Position> {
+        self.history.iter().map(|rope| rope[knot]).collect()
     }
 
-    visited.len() as usize
+    fn render(&self, step: usize) -> String {
+        let rope = self.positions_at(step);
+        let xs = rope.iter().map(|knot| knot.0).chain([0]);
+        let ys = rope.iter().map(|knot| knot.1).chain([0]);
+        let (min_x, max_x) = (xs.clone().min().unwrap(), xs.max().unwrap());
+        let (min_y, max_y) = (ys.clone().min().unwrap(), ys.max().unwrap());
+
+        let label = |i: usize| match i {
+            0 => 'H',
+            1 if rope.len() == 2 => 'T',
+            _ => char::from_digit((i % 10) as u32, 10).unwrap(),
+        };
+
+        (min_y..=max_y)
+            .rev()
+            .map(|y| {
+                (min_x..=max_x)
+                    .map(|x| match rope.iter().position(|&knot| knot == (x, y)) {
+                        Some(i) => label(i),
+                        None if (x, y) == (0, 0) => 's',
+                        None => '.',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+pub fn part1() -> usize {
+    let moves: Vec<Move> = parser::read("data/day9.txt").unwrap();
+    visited_positions(&moves, 1)
+}
+
+pub fn part2() -> usize {
+    let moves: Vec<Move> = parser::read("data/day9.txt").unwrap();
+    visited_positions(&moves, 9)
+}
+
+fn visited_positions(moves: &[Move], rope_length: usize) -> usize {
+    let mut rope = Rope::new(rope_length + 1);
+    for rope_move in moves {
+        rope.apply(rope_move);
+    }
+    rope.visited(rope_length).len()
 }
 
 #[cfg(test)]
@@ -92,7 +183,7 @@ mod tests {
 
     #[test]
     fn sample_input_part_1() {
-        let moves = vec![
+        let moves = [
             Move::Right(4),
             Move::Up(4),
             Move::Left(3),
@@ -107,7 +198,7 @@ mod tests {
 
     #[test]
     fn sample_input_part_2() {
-        let moves = vec![
+        let moves = [
             Move::Right(5),
             Move::Up(8),
             Move::Left(8),
@@ -119,4 +210,68 @@ mod tests {
         ];
         assert_eq!(visited_positions(&moves, 9), 25);
     }
+
+    #[test]
+    fn parsing_diagonals() {
+        assert!(matches!("UR 3".parse::<Move>(), Ok(Move::UpRight(3))));
+        assert!(matches!("DL 1".parse::<Move>(), Ok(Move::DownLeft(1))));
+        assert!("X 1".parse::<Move>().is_err());
+    }
+
+    #[test]
+    fn knot_positions_per_step() {
+        let mut rope = Rope::new(2);
+        rope.apply(&Move::Right(4));
+        rope.apply(&Move::Up(1));
+
+        assert_eq!(rope.steps(), 5);
+        assert_eq!(rope.positions_at(0), &[(0, 0), (0, 0)]);
+        assert_eq!(rope.positions_at(4), &[(4, 0), (3, 0)]);
+        assert_eq!(rope.positions_at(5), &[(4, 1), (3, 0)]);
+        assert_eq!(rope.render(4), "s..TH");
+        assert_eq!(rope.visited(0).len(), 6);
+    }
+
+    #[test]
+    fn diagonal_moves() {
+        let mut rope = Rope::new(2);
+        rope.apply(&Move::UpRight(2));
+
+        assert_eq!(rope.knots(), &[(2, 2), (1, 1)]);
+        assert_eq!(rope.visited(1).len(), 2);
+    }
+
+    #[test]
+    fn larger_example_trace() {
+        let mut rope = Rope::new(10).with_trace();
+        rope.apply(&Move::Right(5));
+
+        assert_eq!(rope.render(rope.steps()), "54321H");
+
+        rope.apply(&Move::Up(8));
+        assert_eq!(rope.knots()[0], (5, 8));
+        assert_eq!(rope.knots()[4], (5, 4));
+        assert_eq!(rope.knots()[9], (0, 0));
+
+        let trace = rope.trace();
+        assert_eq!(trace.len(), 2);
+        assert_eq!(trace[0], "== Right(5) ==\n54321H");
+        assert_eq!(
+            trace[1],
+            [
+                "== Up(8) ==",
+                ".....H",
+                ".....1",
+                ".....2",
+                ".....3",
+                "....54",
+                "...6..",
+                "..7...",
+                ".8....",
+                "9.....",
+            ]
+            .join("\n")
+        );
+        assert_eq!(Rope::new(2).trace(), &[] as &[String]);
+    }
 }
@@ -17,8 +17,8 @@ fn parse_command_line_args() -> (usize, usize) {
     }
 }
 
-fn run(day: usize, part: usize) -> usize {
-    let solution = match (day, part) {
+fn run(day: usize, part: usize) -> String {
+    let solution: fn() -> usize = match (day, part) {
         (1, 1) => day1::part1,
         (1, 2) => day1::part2,
         (2, 1) => day2::part1,
@@ -27,8 +27,9 @@ fn run(day: usize, part: usize) -> usize {
         (3, 2) => day3::part2,
         (4, 1) => day4::part1,
         (4, 2) => day4::part2,
-        (5, 1) => day5::part1,
-        (5, 2) => day5::part2,
+        // Answers that are words rather than numbers
+        (5, 1) => return day5::part1(),
+        (5, 2) => return day5::part2(),
         (6, 1) => day6::part1,
         (6, 2) => day6::part2,
         (7, 1) => day7::part1,
@@ -69,7 +70,7 @@ fn run(day: usize, part: usize) -> usize {
         (24, 2) => day24::part2,
         _ => panic!("No solution yet for day {} and part {}", day, part),
     };
-    solution()
+    solution().to_string()
 }
 fn main() {
     let (day, part) = parse_command_line_args();
@@ -0,0 +1,32 @@
+// Combines x = r1 (mod m1) and x = r2 (mod m2) into x = r (mod lcm), None when they conflict
+pub fn crt((r1, m1): (i128, i128), (r2, m2): (i128, i128)) -> Option<(i128, i128)> {
+    let (gcd, p, _) = extended_gcd(m1, m2);
+    if (r2 - r1) % gcd != 0 {
+        return None;
+    }
+
+    let lcm = m1 / gcd * m2;
+    let k = ((r2 - r1) / gcd * p).rem_euclid(m2 / gcd);
+    Some(((r1 + m1 * k).rem_euclid(lcm), lcm))
+}
+
+pub fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        return (a, 1, 0);
+    }
+    let (gcd, x, y) = extended_gcd(b, a % b);
+    (gcd, y, x - (a / b) * y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combines_congruences() {
+        assert_eq!(crt((2, 3), (3, 5)), Some((8, 15)));
+        assert_eq!(crt((2, 4), (0, 6)), Some((6, 12)));
+        assert_eq!(crt((1, 4), (0, 6)), None);
+        assert_eq!(extended_gcd(12, 18).0, 6);
+    }
+}
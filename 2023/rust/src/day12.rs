@@ -1,12 +1,18 @@
+mod line;
+// Puzzle tooling on top of the line solver, not needed for the puzzle answers
+#[allow(dead_code)]
+mod nonogram;
+
 use std::str::FromStr;
 
-use cached::proc_macro::cached;
+use line::Cell;
 
 use crate::parser;
 
+// Damaged springs are filled cells, operational ones are empty
 #[derive(Debug)]
 struct Record {
-    field: String,
+    field: Vec<Cell>,
     damaged: Vec<usize>,
 }
 
@@ -19,7 +25,11 @@ impl FromStr for Record {
             return Err("Invalid input format".to_string());
         }
 
-        let field = parts[0].to_string();
+        let field: Vec<Cell> = parts[0]
+            .chars()
+            .map(Cell::from_char)
+            .collect::<Option<_>>()
+            .ok_or("Invalid spring condition".to_string())?;
         let damaged: Result<Vec<usize>, _> =
             parts[1].split(',').map(|x| x.parse::<usize>()).collect();
 
@@ -41,47 +51,13 @@ pub fn part2() -> usize {
     sum_of_arrangements(&unfolded_records)
 }
 
-fn sum_of_arrangements(records: &Vec<Record>) -> usize {
+fn sum_of_arrangements(records: &[Record]) -> usize {
     records
         .iter()
-        .map(|record| arrangements(record.field.clone(), record.damaged.clone()))
+        .map(|record| line::arrangements(&record.field, &record.damaged))
         .sum()
 }
 
-#[cached]
-fn arrangements(field: String, damaged: Vec<usize>) -> usize {
-    if field.len() == 0 {
-        return if damaged.len() == 0 { 1 } else { 0 };
-    }
-
-    let mut count = 0;
-
-    let head = field.chars().nth(0).unwrap();
-    if head == '.' || head == '?' {
-        count += arrangements(field[1..].to_string(), damaged.clone());
-    }
-
-    if head == '#' || head == '?' {
-        if let Some(damage) = damaged.first() {
-            let can_fit_damage =
-                *damage <= field.len() && field[0..*damage].chars().all(|c| c == '#' || c == '?');
-            let followed_by_space =
-                *damage == field.len() || field.chars().nth(*damage) != Some('#');
-
-            if can_fit_damage && followed_by_space {
-                let damage_size = if *damage == field.len() {
-                    *damage
-                } else {
-                    *damage + 1
-                };
-                count += arrangements(field[damage_size..].to_string(), damaged[1..].to_vec());
-            }
-        }
-    }
-
-    count
-}
-
 fn unfold(records: Vec<Record>) -> Vec<Record> {
     records
         .iter()
@@ -90,8 +66,8 @@ fn unfold(records: Vec<Record>) -> Vec<Record> {
             let mut damaged = record.damaged.clone();
 
             for _ in 0..4 {
-                field.push('?');
-                field.push_str(&record.field);
+                field.push(Cell::Unknown);
+                field.extend(&record.field);
 
                 damaged.extend(record.damaged.clone());
             }
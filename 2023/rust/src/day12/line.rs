@@ -0,0 +1,174 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Empty,
+    Filled,
+    Unknown,
+}
+
+impl Cell {
+    pub fn from_char(c: char) -> Option<Cell> {
+        match c {
+            '.' => Some(Cell::Empty),
+            '#' => Some(Cell::Filled),
+            '?' => Some(Cell::Unknown),
+            _ => None,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn to_char(self) -> char {
+        match self {
+            Cell::Empty => '.',
+            Cell::Filled => '#',
+            Cell::Unknown => '?',
+        }
+    }
+}
+
+// ways[i][j] is the number of ways to fill cells[i..] with runs[j..]
+struct Table<'a> {
+    cells: &'a [Cell],
+    runs: &'a [usize],
+    empties_before: Vec<usize>,
+    ways: Vec<Vec<usize>>,
+}
+
+impl<'a> Table<'a> {
+    fn new(cells: &'a [Cell], runs: &'a [usize]) -> Self {
+        let (length, count) = (cells.len(), runs.len());
+
+        let mut empties_before = vec![0; length + 1];
+        for (i, &cell) in cells.iter().enumerate() {
+            empties_before[i + 1] = empties_before[i] + usize::from(cell == Cell::Empty);
+        }
+
+        let mut table = Table {
+            cells,
+            runs,
+            empties_before,
+            ways: vec![vec![0; count + 1]; length + 1],
+        };
+        table.ways[length][count] = 1;
+
+        for i in (0..length).rev() {
+            for j in (0..=count).rev() {
+                let mut ways = 0;
+                if cells[i] != Cell::Filled {
+                    ways += table.ways[i + 1][j];
+                }
+                if let Some(next) = table.run_end(i, j) {
+                    ways += table.ways[next][j + 1];
+                }
+                table.ways[i][j] = ways;
+            }
+        }
+
+        table
+    }
+
+    // Where the rest of the line starts when run j is placed at cell i, gap included
+    fn run_end(&self, i: usize, j: usize) -> Option<usize> {
+        let run = *self.runs.get(j)?;
+        let end = i + run;
+
+        let fits = self.cells[i] != Cell::Empty
+            && end <= self.cells.len()
+            && self.empties_before[end] == self.empties_before[i]
+            && self.cells.get(end) != Some(&Cell::Filled);
+        fits.then_some((end + 1).min(self.cells.len()))
+    }
+}
+
+pub fn arrangements(cells: &[Cell], runs: &[usize]) -> usize {
+    Table::new(cells, runs).ways[0][0]
+}
+
+// Cells that are the same in every arrangement, None when there is no arrangement at all
+#[allow(dead_code)]
+pub fn forced_cells(cells: &[Cell], runs: &[usize]) -> Option<Vec<Cell>> {
+    let table = Table::new(cells, runs);
+    if table.ways[0][0] == 0 {
+        return None;
+    }
+
+    // Walk forwards through the states that both can be reached and can be completed
+    let (length, count) = (cells.len(), runs.len());
+    let mut reachable = vec![vec![false; count + 1]; length + 1];
+    let mut can_be_filled = vec![false; length];
+    let mut can_be_empty = vec![false; length];
+    reachable[0][0] = true;
+
+    for i in 0..length {
+        for j in 0..=count {
+            if !reachable[i][j] {
+                continue;
+            }
+
+            if cells[i] != Cell::Filled && table.ways[i + 1][j] > 0 {
+                can_be_empty[i] = true;
+                reachable[i + 1][j] = true;
+            }
+            if let Some(next) = table
+                .run_end(i, j)
+                .filter(|&next| table.ways[next][j + 1] > 0)
+            {
+                let end = i + runs[j];
+                can_be_filled[i..end]
+                    .iter_mut()
+                    .for_each(|cell| *cell = true);
+                if end < length {
+                    can_be_empty[end] = true;
+                }
+                reachable[next][j + 1] = true;
+            }
+        }
+    }
+
+    Some(
+        can_be_filled
+            .into_iter()
+            .zip(can_be_empty)
+            .map(|filled_or_empty| match filled_or_empty {
+                (true, false) => Cell::Filled,
+                (false, true) => Cell::Empty,
+                _ => Cell::Unknown,
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(s: &str) -> Vec<Cell> {
+        s.chars().map(|c| Cell::from_char(c).unwrap()).collect()
+    }
+
+    fn to_string(cells: &[Cell]) -> String {
+        cells.iter().map(|cell| cell.to_char()).collect()
+    }
+
+    #[test]
+    fn counts_arrangements() {
+        assert_eq!(arrangements(&cells("???.###"), &[1, 1, 3]), 1);
+        assert_eq!(arrangements(&cells("?###????????"), &[3, 2, 1]), 10);
+        assert_eq!(arrangements(&cells("????"), &[]), 1);
+        assert_eq!(arrangements(&cells(""), &[]), 1);
+        assert_eq!(arrangements(&cells("#.#"), &[3]), 0);
+    }
+
+    #[test]
+    fn finds_forced_cells() {
+        let forced = forced_cells(&cells("??????????"), &[8]).unwrap();
+        assert_eq!(to_string(&forced), "??######??");
+
+        let forced = forced_cells(&cells("???.###"), &[1, 1, 3]).unwrap();
+        assert_eq!(to_string(&forced), "#.#.###");
+
+        let forced = forced_cells(&cells("?#???"), &[2]).unwrap();
+        assert_eq!(to_string(&forced), "?#?..");
+
+        assert_eq!(forced_cells(&cells("##.?"), &[3]), None);
+    }
+}
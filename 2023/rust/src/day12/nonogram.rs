@@ -0,0 +1,122 @@
+use super::line::{forced_cells, Cell};
+
+type Grid = Vec<Vec<Cell>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nonogram {
+    pub rows: Vec<Vec<usize>>,
+    pub columns: Vec<Vec<usize>>,
+}
+
+impl Nonogram {
+    // Line passes until nothing changes, then guesses the first unknown cell and backtracks
+    pub fn solve(&self) -> Option<Grid> {
+        let grid = vec![vec![Cell::Unknown; self.columns.len()]; self.rows.len()];
+        self.search(grid)
+    }
+
+    fn search(&self, mut grid: Grid) -> Option<Grid> {
+        self.propagate(&mut grid)?;
+
+        let Some((x, y)) = first_unknown(&grid) else {
+            return Some(grid);
+        };
+        [Cell::Filled, Cell::Empty].into_iter().find_map(|guess| {
+            let mut guessed = grid.clone();
+            guessed[y][x] = guess;
+            self.search(guessed)
+        })
+    }
+
+    // None when some row or column can no longer be completed
+    fn propagate(&self, grid: &mut Grid) -> Option<()> {
+        let mut dirty_rows = vec![true; self.rows.len()];
+        let mut dirty_columns = vec![true; self.columns.len()];
+
+        while dirty_rows.contains(&true) || dirty_columns.contains(&true) {
+            for y in 0..self.rows.len() {
+                if !std::mem::take(&mut dirty_rows[y]) {
+                    continue;
+                }
+                let forced = forced_cells(&grid[y], &self.rows[y])?;
+                for (x, cell) in forced.into_iter().enumerate() {
+                    if cell != grid[y][x] {
+                        grid[y][x] = cell;
+                        dirty_columns[x] = true;
+                    }
+                }
+            }
+
+            for x in 0..self.columns.len() {
+                if !std::mem::take(&mut dirty_columns[x]) {
+                    continue;
+                }
+                let column: Vec<Cell> = grid.iter().map(|row| row[x]).collect();
+                let forced = forced_cells(&column, &self.columns[x])?;
+                for (y, cell) in forced.into_iter().enumerate() {
+                    if cell != grid[y][x] {
+                        grid[y][x] = cell;
+                        dirty_rows[y] = true;
+                    }
+                }
+            }
+        }
+
+        Some(())
+    }
+}
+
+fn first_unknown(grid: &Grid) -> Option<(usize, usize)> {
+    grid.iter().enumerate().find_map(|(y, row)| {
+        row.iter()
+            .position(|&cell| cell == Cell::Unknown)
+            .map(|x| (x, y))
+    })
+}
+
+pub fn render(grid: &Grid) -> String {
+    grid.iter()
+        .map(|row| row.iter().map(|cell| cell.to_char()).collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solves_by_line_passes() {
+        let nonogram = Nonogram {
+            rows: vec![vec![1, 1], vec![5], vec![1, 1], vec![3], vec![1]],
+            columns: vec![vec![1], vec![4], vec![1, 2], vec![4], vec![1]],
+        };
+
+        let grid = nonogram.solve().unwrap();
+
+        assert_eq!(render(&grid), ".#.#.\n#####\n.#.#.\n.###.\n..#..");
+    }
+
+    #[test]
+    fn solves_by_backtracking() {
+        // Both diagonals satisfy every clue, so a guess is needed
+        let nonogram = Nonogram {
+            rows: vec![vec![1], vec![1]],
+            columns: vec![vec![1], vec![1]],
+        };
+
+        let grid = nonogram.solve().unwrap();
+
+        assert_eq!(render(&grid), "#.\n.#");
+    }
+
+    #[test]
+    fn contradictory_clues() {
+        let nonogram = Nonogram {
+            rows: vec![vec![2], vec![]],
+            columns: vec![vec![], vec![1]],
+        };
+
+        assert_eq!(nonogram.solve(), None);
+    }
+}
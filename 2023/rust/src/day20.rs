@@ -1,8 +1,12 @@
+mod export;
 mod module;
 mod simulation;
 
-use std::collections::HashSet;
+use std::collections::HashMap;
 
+use module::ModuleKind;
+
+use crate::congruence::crt;
 use crate::parser;
 
 pub fn part1() -> usize {
@@ -14,66 +18,267 @@ pub fn part1() -> usize {
 pub fn part2() -> usize {
     let lines: Vec<String> = parser::read("data/day20.txt").unwrap();
     let mut sim = simulation::Simulation::parse(&lines);
-    presses_until_rx(&mut sim)
+    presses_until_rx(&mut sim).unwrap()
 }
 
 fn pulse_mults(sim: &mut simulation::Simulation) -> usize {
+    let (mut high_pulses, mut low_pulses) = (0, 0);
     for _ in 0..1000 {
-        sim.press_button();
+        let pulses = sim.step();
+        let high = pulses.iter().filter(|pulse| pulse.on).count();
+        high_pulses += high;
+        low_pulses += pulses.len() - high;
     }
-    let high_pulses = sim.pulses.iter().filter(|pulse| pulse.on).count();
-    let low_pulses = sim.pulses.iter().filter(|pulse| !pulse.on).count();
 
     high_pulses * low_pulses
 }
 
-fn presses_until_rx(sim: &mut simulation::Simulation) -> usize {
-    let mut presses = 0;
-    let mut remaining_fan_in_nodes = HashSet::from([
-        "qz".to_string(),
-        "cq".to_string(),
-        "jx".to_string(),
-        "tt".to_string(),
-    ]);
-    let mut activation_presses = Vec::new();
-
-    while !remaining_fan_in_nodes.is_empty() {
-        presses += 1;
-        sim.press_button();
-
-        if let Some(pulse) = sim.pulses.iter().find(|pulse| {
-            pulse.on && pulse.to == "qn" && remaining_fan_in_nodes.contains(&pulse.from)
-        }) {
-            activation_presses.push(presses);
-            remaining_fan_in_nodes.remove(&pulse.from);
+// Returns the first press whose resulting state shows up again, and after how many presses
+#[allow(dead_code)]
+fn global_cycle(sim: &mut simulation::Simulation, max_presses: usize) -> Option<(usize, usize)> {
+    let mut seen: HashMap<simulation::Snapshot, usize> = HashMap::from([(sim.snapshot(), 0)]);
+    for presses in 1..=max_presses {
+        sim.step();
+        if let Some(start) = seen.insert(sim.snapshot(), presses) {
+            return Some((start, presses - start));
         }
-
-        sim.pulses.clear();
     }
+    None
+}
+
+// Enough presses to see every counter of a real input fire three times
+const MAX_PRESSES: usize = 100_000;
 
-    lcm(&activation_presses)
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DecomposeError {
+    NoDriver,
+    SeveralDrivers(Vec<String>),
+    DriverNotConjunction(String),
+    NoPeriod(String),
+    IrregularPeriod(String),
+    StaysHigh(String),
+    IncompatibleCounters,
 }
 
-fn lcm(nums: &[usize]) -> usize {
-    if nums.len() == 1 {
-        return nums[0];
+// An input of the rx driver that sends a high pulse on presses first, first + period, ...
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Counter {
+    first: usize,
+    period: usize,
+}
+
+fn presses_until_rx(sim: &mut simulation::Simulation) -> Result<usize, DecomposeError> {
+    let driver = match &sim.inputs_of("rx")[..] {
+        [] => return Err(DecomposeError::NoDriver),
+        [driver] => driver.clone(),
+        drivers => return Err(DecomposeError::SeveralDrivers(drivers.to_vec())),
+    };
+    if sim.kind_of(&driver) != Some(ModuleKind::Conjunction) {
+        return Err(DecomposeError::DriverNotConjunction(driver));
     }
-    let a = nums[0];
-    let b = lcm(&nums[1..]);
-    a * b / gcd_of_two_numbers(a, b)
+
+    let inputs = sim.inputs_of(&driver);
+    if inputs.is_empty() {
+        return Err(DecomposeError::NoPeriod(driver));
+    }
+    let counters = find_counters(sim, &driver, &inputs)?;
+    combine_counters(&counters).ok_or(DecomposeError::IncompatibleCounters)
 }
 
-fn gcd_of_two_numbers(a: usize, b: usize) -> usize {
-    if b == 0 {
-        return a;
+fn find_counters(
+    sim: &mut simulation::Simulation,
+    driver: &str,
+    inputs: &[String],
+) -> Result<Vec<Counter>, DecomposeError> {
+    let mut high_presses: HashMap<&str, Vec<usize>> = inputs
+        .iter()
+        .map(|input| (input.as_str(), vec![]))
+        .collect();
+
+    for presses in 1..=MAX_PRESSES {
+        let pulses = sim.step();
+
+        let mut last_pulses: HashMap<&str, bool> = HashMap::new();
+        for pulse in pulses.iter().filter(|pulse| pulse.to == driver) {
+            last_pulses.insert(pulse.from.as_str(), pulse.on);
+            if let Some(seen) = high_presses.get_mut(pulse.from.as_str()) {
+                if pulse.on && seen.last() != Some(&presses) {
+                    seen.push(presses);
+                }
+            }
+        }
+
+        // The driver only sees every counter high at once if each of them resets
+        // within the press it fires in
+        for (input, seen) in &high_presses {
+            if seen.last() == Some(&presses) && last_pulses.get(input) == Some(&true) {
+                return Err(DecomposeError::StaysHigh(input.to_string()));
+            }
+        }
+        if high_presses.values().all(|seen| seen.len() >= 3) {
+            break;
+        }
     }
-    gcd_of_two_numbers(b, a % b)
+
+    inputs
+        .iter()
+        .map(|input| match high_presses[input.as_str()][..] {
+            [first, second, third, ..] if third - second == second - first => Ok(Counter {
+                first,
+                period: second - first,
+            }),
+            [_, _, _, ..] => Err(DecomposeError::IrregularPeriod(input.clone())),
+            _ => Err(DecomposeError::NoPeriod(input.clone())),
+        })
+        .collect()
+}
+
+fn combine_counters(counters: &[Counter]) -> Option<usize> {
+    let (residue, modulus) = counters.iter().try_fold((0, 1), |congruence, counter| {
+        crt(
+            congruence,
+            (
+                (counter.first % counter.period) as i128,
+                counter.period as i128,
+            ),
+        )
+    })?;
+
+    let earliest = counters.iter().map(|counter| counter.first).max()? as i128;
+    Some((earliest + (residue - earliest).rem_euclid(modulus)) as usize)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn simulation(lines: &[&str]) -> simulation::Simulation {
+        let lines: Vec<String> = lines.iter().map(|s| s.to_string()).collect();
+        simulation::Simulation::parse(&lines)
+    }
+
+    fn presses_until_low_rx(sim: &mut simulation::Simulation) -> usize {
+        let mut presses = 0;
+        loop {
+            presses += 1;
+            if sim.step().iter().any(|pulse| pulse.to == "rx" && !pulse.on) {
+                return presses;
+            }
+        }
+    }
+
+    // Two resetting counters of 3 and 5 presses, each behind an inverter
+    const COUNTERS: [&str; 11] = [
+        "broadcaster -> a, e",
+        "%a -> b, k",
+        "%b -> k",
+        "&k -> a, n",
+        "&n -> con",
+        "%e -> f, j",
+        "%f -> g",
+        "%g -> j",
+        "&j -> f, e, m",
+        "&m -> con",
+        "&con -> rx",
+    ];
+
+    #[test]
+    fn discovers_counters() {
+        let mut sim = simulation(&COUNTERS);
+        let inputs = sim.inputs_of("con");
+        let counters = find_counters(&mut sim, "con", &inputs).unwrap();
+
+        assert_eq!(sim.inputs_of("rx"), vec!["con".to_string()]);
+        assert_eq!(
+            counters,
+            vec![
+                Counter {
+                    first: 5,
+                    period: 5
+                },
+                Counter {
+                    first: 3,
+                    period: 3
+                }
+            ]
+        );
+        assert_eq!(presses_until_rx(&mut simulation(&COUNTERS)), Ok(15));
+        assert_eq!(presses_until_low_rx(&mut simulation(&COUNTERS)), 15);
+    }
+
+    #[test]
+    fn combines_offsets() {
+        let counters = [
+            Counter {
+                first: 2,
+                period: 4,
+            },
+            Counter {
+                first: 8,
+                period: 5,
+            },
+        ];
+        assert_eq!(combine_counters(&counters), Some(18));
+
+        let counters = [
+            Counter {
+                first: 1,
+                period: 2,
+            },
+            Counter {
+                first: 2,
+                period: 4,
+            },
+        ];
+        assert_eq!(combine_counters(&counters), None);
+    }
+
+    #[test]
+    fn rejects_other_structures() {
+        let mut sim = simulation(&["broadcaster -> a", "%a -> rx"]);
+        assert_eq!(
+            presses_until_rx(&mut sim),
+            Err(DecomposeError::DriverNotConjunction("a".to_string()))
+        );
+
+        let mut sim = simulation(&["broadcaster -> a", "%a -> b"]);
+        assert_eq!(presses_until_rx(&mut sim), Err(DecomposeError::NoDriver));
+
+        let mut sim = simulation(&[
+            "broadcaster -> a",
+            "%a -> b, con",
+            "%b -> con",
+            "&con -> rx",
+        ]);
+        assert_eq!(
+            presses_until_rx(&mut sim),
+            Err(DecomposeError::StaysHigh("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn stepping_and_snapshots() {
+        let mut sim = simulation(&[
+            "broadcaster -> a",
+            "%a -> inv, con",
+            "&inv -> b",
+            "%b -> con",
+            "&con -> output",
+        ]);
+        let initial = sim.snapshot();
+
+        let pulses = sim.step();
+        assert_eq!(pulses.len(), 8);
+        assert_eq!(pulses[0].from, "button");
+        assert!(sim.pulses.is_empty());
+        assert_ne!(sim.snapshot(), initial);
+
+        // Conjunction memories never go back to their initial all-low state
+        let first_press = sim.snapshot();
+        assert_eq!(global_cycle(&mut sim, 10), Some((0, 4)));
+        assert_eq!(sim.snapshot(), first_press);
+    }
+
     #[test]
     fn sample_input_part_1() {
         let lines = vec![
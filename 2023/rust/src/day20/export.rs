@@ -0,0 +1,128 @@
+use super::{module::ModuleKind, simulation::Simulation};
+
+// Modules receiving pulses without being defined, like rx or output
+#[allow(dead_code)]
+fn sinks(sim: &Simulation) -> Vec<String> {
+    let mut sinks: Vec<String> = sim
+        .labels()
+        .iter()
+        .flat_map(|label| sim.destinations_of(label).to_vec())
+        .filter(|destination| sim.kind_of(destination).is_none())
+        .collect();
+    sinks.sort();
+    sinks.dedup();
+    sinks
+}
+
+#[allow(dead_code)]
+fn edges(sim: &Simulation) -> Vec<(String, String)> {
+    sim.labels()
+        .into_iter()
+        .flat_map(|label| {
+            sim.destinations_of(&label)
+                .iter()
+                .map(|destination| (label.clone(), destination.clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[allow(dead_code)]
+pub fn to_dot(sim: &Simulation) -> String {
+    let mut lines = vec!["digraph modules {".to_string()];
+
+    for label in sim.labels() {
+        let shape = match sim.kind_of(&label).unwrap() {
+            ModuleKind::Broadcaster => "doublecircle",
+            ModuleKind::FlipFlop => "box",
+            ModuleKind::Conjunction => "diamond",
+        };
+        lines.push(format!("    {} [shape={}];", label, shape));
+    }
+    for sink in sinks(sim) {
+        lines.push(format!("    {} [shape=ellipse];", sink));
+    }
+    for (from, to) in edges(sim) {
+        lines.push(format!("    {} -> {};", from, to));
+    }
+
+    lines.push("}".to_string());
+    lines.join("\n")
+}
+
+#[allow(dead_code)]
+pub fn to_mermaid(sim: &Simulation) -> String {
+    let mut lines = vec!["flowchart LR".to_string()];
+
+    for label in sim.labels() {
+        let node = match sim.kind_of(&label).unwrap() {
+            ModuleKind::Broadcaster => format!("(({}))", label),
+            ModuleKind::FlipFlop => format!("[{}]", label),
+            ModuleKind::Conjunction => format!("{{{}}}", label),
+        };
+        lines.push(format!("    {}{}", label, node));
+    }
+    for sink in sinks(sim) {
+        lines.push(format!("    {}([{}])", sink, sink));
+    }
+    for (from, to) in edges(sim) {
+        lines.push(format!("    {} --> {}", from, to));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Simulation {
+        let lines: Vec<String> = [
+            "broadcaster -> a",
+            "%a -> inv, con",
+            "&inv -> b",
+            "%b -> con",
+            "&con -> output",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        Simulation::parse(&lines)
+    }
+
+    #[test]
+    fn dot() {
+        assert_eq!(
+            to_dot(&sample()),
+            [
+                "digraph modules {",
+                "    a [shape=box];",
+                "    b [shape=box];",
+                "    broadcaster [shape=doublecircle];",
+                "    con [shape=diamond];",
+                "    inv [shape=diamond];",
+                "    output [shape=ellipse];",
+                "    a -> inv;",
+                "    a -> con;",
+                "    b -> con;",
+                "    broadcaster -> a;",
+                "    con -> output;",
+                "    inv -> b;",
+                "}",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn mermaid() {
+        let mermaid = to_mermaid(&sample());
+
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains("    broadcaster((broadcaster))\n"));
+        assert!(mermaid.contains("    a[a]\n"));
+        assert!(mermaid.contains("    con{con}\n"));
+        assert!(mermaid.contains("    output([output])\n"));
+        assert!(mermaid.ends_with("    inv --> b"));
+    }
+}
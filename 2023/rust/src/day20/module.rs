@@ -2,8 +2,18 @@ use std::{collections::HashMap, str::FromStr};
 
 use super::simulation::Pulse;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleKind {
+    Broadcaster,
+    FlipFlop,
+    Conjunction,
+}
+
 pub trait Module {
     fn process(&mut self, pulse: Pulse) -> Vec<Pulse>;
+    fn destinations(&self) -> &[String];
+    fn kind(&self) -> ModuleKind;
+    fn state(&self) -> Vec<bool>;
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -23,6 +33,18 @@ impl Module for Broadcaster {
             })
             .collect()
     }
+
+    fn destinations(&self) -> &[String] {
+        &self.destinations
+    }
+
+    fn kind(&self) -> ModuleKind {
+        ModuleKind::Broadcaster
+    }
+
+    fn state(&self) -> Vec<bool> {
+        vec![]
+    }
 }
 
 impl FromStr for Broadcaster {
@@ -61,6 +83,18 @@ impl Module for FlipFlop {
             })
             .collect()
     }
+
+    fn destinations(&self) -> &[String] {
+        &self.destinations
+    }
+
+    fn kind(&self) -> ModuleKind {
+        ModuleKind::FlipFlop
+    }
+
+    fn state(&self) -> Vec<bool> {
+        vec![self.on]
+    }
 }
 
 impl FromStr for FlipFlop {
@@ -97,6 +131,21 @@ impl Module for Conjunction {
             })
             .collect()
     }
+
+    fn destinations(&self) -> &[String] {
+        &self.destinations
+    }
+
+    fn kind(&self) -> ModuleKind {
+        ModuleKind::Conjunction
+    }
+
+    // Memory of every input, in the order of the input labels
+    fn state(&self) -> Vec<bool> {
+        let mut memory: Vec<(&String, &bool)> = self.input_pulses.iter().collect();
+        memory.sort();
+        memory.into_iter().map(|(_, on)| *on).collect()
+    }
 }
 
 impl FromStr for Conjunction {
@@ -206,7 +255,7 @@ mod tests {
                 on: true,
             });
 
-            assert_eq!(flip_flop.on, false);
+            assert!(!flip_flop.on);
             assert_eq!(result, vec![]);
         }
 
@@ -224,7 +273,7 @@ mod tests {
                 on: false,
             });
 
-            assert_eq!(flip_flop.on, true);
+            assert!(flip_flop.on);
             assert_eq!(
                 result,
                 vec![
@@ -256,7 +305,7 @@ mod tests {
                 on: false,
             });
 
-            assert_eq!(flip_flop.on, false);
+            assert!(!flip_flop.on);
             assert_eq!(
                 result,
                 vec![
@@ -3,7 +3,7 @@ use std::{
     str::FromStr,
 };
 
-use super::module::{Broadcaster, Conjunction, FlipFlop, Module};
+use super::module::{Broadcaster, Conjunction, FlipFlop, Module, ModuleKind};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Pulse {
@@ -12,13 +12,17 @@ pub struct Pulse {
     pub on: bool,
 }
 
+// Flip-flop states and conjunction memories of every module, sorted by label
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Snapshot(Vec<(String, Vec<bool>)>);
+
 pub struct Simulation {
     pub pulses: Vec<Pulse>,
     pub modules: HashMap<String, Box<dyn Module>>,
 }
 
 impl Simulation {
-    pub fn press_button(&mut self) {
+    fn press_button(&mut self) {
         let mut to_process = VecDeque::new();
         to_process.push_back(Pulse {
             from: "button".to_string(),
@@ -38,7 +42,52 @@ impl Simulation {
         }
     }
 
-    pub fn parse(lines: &Vec<String>) -> Self {
+    // Presses the button once and hands over the pulses of that press only
+    pub fn step(&mut self) -> Vec<Pulse> {
+        self.press_button();
+        std::mem::take(&mut self.pulses)
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let mut states: Vec<(String, Vec<bool>)> = self
+            .modules
+            .iter()
+            .map(|(label, module)| (label.clone(), module.state()))
+            .collect();
+        states.sort();
+        Snapshot(states)
+    }
+
+    #[allow(dead_code)]
+    pub fn labels(&self) -> Vec<String> {
+        let mut labels: Vec<String> = self.modules.keys().cloned().collect();
+        labels.sort();
+        labels
+    }
+
+    #[allow(dead_code)]
+    pub fn destinations_of(&self, label: &str) -> &[String] {
+        self.modules
+            .get(label)
+            .map_or(&[], |module| module.destinations())
+    }
+
+    pub fn kind_of(&self, label: &str) -> Option<ModuleKind> {
+        self.modules.get(label).map(|module| module.kind())
+    }
+
+    pub fn inputs_of(&self, label: &str) -> Vec<String> {
+        let mut inputs: Vec<String> = self
+            .modules
+            .iter()
+            .filter(|(_, module)| module.destinations().iter().any(|d| d == label))
+            .map(|(input, _)| input.clone())
+            .collect();
+        inputs.sort();
+        inputs
+    }
+
+    pub fn parse(lines: &[String]) -> Self {
         let broadcaster: Broadcaster = Simulation::filter_and_parse(lines, "broadcaster")
             .next()
             .unwrap();
@@ -71,7 +120,7 @@ impl Simulation {
             });
         }
 
-        let mut conjunctions: Vec<Conjunction> = conjunctions.iter().cloned().collect();
+        let mut conjunctions: Vec<Conjunction> = conjunctions.to_vec();
         for conjunction in conjunctions.iter_mut() {
             for destination in inputs.get(&conjunction.label).unwrap_or(&Vec::new()) {
                 conjunction.input_pulses.insert(destination.clone(), false);
@@ -93,10 +142,7 @@ impl Simulation {
         }
     }
 
-    fn filter_and_parse<'a, T>(
-        lines: &'a Vec<String>,
-        prefix: &'a str,
-    ) -> impl Iterator<Item = T> + 'a
+    fn filter_and_parse<'a, T>(lines: &'a [String], prefix: &'a str) -> impl Iterator<Item = T> + 'a
     where
         T: FromStr,
         <T as FromStr>::Err: std::fmt::Debug,
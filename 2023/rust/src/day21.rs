@@ -1,4 +1,4 @@
-use std::{collections::HashSet, usize};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use crate::parser;
 
@@ -9,10 +9,12 @@ pub fn part1() -> usize {
 }
 
 pub fn part2() -> usize {
-    calculate_solution()
+    let lines: Vec<String> = parser::read("data/day21.txt").unwrap();
+    let map = parse(&lines);
+    plots_after_any_steps(&map, 26501365).unwrap()
 }
 
-fn plots_after_steps(map: &Vec<Vec<char>>, steps: usize) -> usize {
+fn plots_after_steps(map: &[Vec<char>], steps: usize) -> usize {
     let width = map[0].len() as isize;
     let height = map.len() as isize;
 
@@ -50,71 +52,98 @@ fn plots_after_steps(map: &Vec<Vec<char>>, steps: usize) -> usize {
     visited.len()
 }
 
-fn calculate_solution() -> usize {
-    // println!("{}", quadractic(1));
-    // println!("{}", infinite_plots_after_steps(map, 65 + 131 * 1));
-    // println!("{}", quadractic(2));
-    // println!("{}", infinite_plots_after_steps(map, 65 + 131 * 2));
-    // println!("{}", quadractic(3));
-    // println!("{}", infinite_plots_after_steps(map, 65 + 131 * 3));
-    // println!("{}", quadractic(4));
-    // println!("{}", infinite_plots_after_steps(map, 65 + 131 * 4));
-    // println!("{}", quadractic(5));
-    // quadractic(25501365 / 131)
-    let blah = 65 + (131 * 202300);
-    println!("{blah}");
-
-    let blah = (blah - 65) / 131;
-    println!("{blah}");
-
-    quadractic(202301)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GardenError {
+    NotSquare,
+    StartNotCentered,
+    BlockedStartLines,
+    NotQuadratic,
 }
 
-fn quadractic(x: usize) -> usize {
-    14898 * x.pow(2) - 14799 * x + 3671
+// The count grows quadratically in the number of whole gardens crossed, as long as the
+// start sits in the middle of a square garden with a clear row and column to the edges
+fn validate(map: &[Vec<char>]) -> Result<(), GardenError> {
+    let size = map.len();
+    if size.is_multiple_of(2) || map.iter().any(|row| row.len() != size) {
+        return Err(GardenError::NotSquare);
+    }
+
+    let (x, y) = position(map, 'S');
+    let center = (size / 2) as isize;
+    if (x, y) != (center, center) {
+        return Err(GardenError::StartNotCentered);
+    }
+
+    let center = size / 2;
+    if map[center].contains(&'#') || map.iter().any(|row| row[center] == '#') {
+        return Err(GardenError::BlockedStartLines);
+    }
+    Ok(())
 }
 
-#[allow(dead_code)]
-fn infinite_plots_after_steps(map: &Vec<Vec<char>>, steps: usize) -> usize {
+fn plots_after_any_steps(map: &[Vec<char>], steps: usize) -> Result<usize, GardenError> {
+    validate(map)?;
+
+    let size = map.len();
+    let (gardens, remainder) = (steps / size, steps % size);
+    if gardens < 4 {
+        return Ok(infinite_plots_after_steps(map, steps));
+    }
+
+    // Samples after 1 to 4 extra gardens, the first one being skipped as a warm-up
+    let samples: Vec<i128> = (1..=4)
+        .map(|k| infinite_plots_after_steps(map, remainder + k * size) as i128)
+        .collect();
+    let difference = samples[1] - samples[0];
+    let second_difference = samples[2] - 2 * samples[1] + samples[0];
+    let at = |n: i128| samples[0] + n * difference + n * (n - 1) / 2 * second_difference;
+
+    if at(3) != samples[3] {
+        return Err(GardenError::NotQuadratic);
+    }
+    Ok(at(gardens as i128 - 1) as usize)
+}
+
+// Breadth first search over the tiled garden, a plot is reachable in exactly `steps`
+// when it is reachable in fewer steps of the same parity
+fn infinite_plots_after_steps(map: &[Vec<char>], steps: usize) -> usize {
     let width = map[0].len() as isize;
     let height = map.len() as isize;
 
     let start = position(map, 'S');
-    let mut visited = HashSet::new();
-    visited.insert(start);
+    let mut distances = HashMap::from([(start, 0)]);
+    let mut queue = VecDeque::from([start]);
 
     let directions = [(1, 0), (-1, 0), (0, 1), (0, -1)];
-    for _ in 0..steps {
-        let mut new_visited = HashSet::new();
+    while let Some((x, y)) = queue.pop_front() {
+        let distance = distances[&(x, y)];
+        if distance == steps {
+            continue;
+        }
 
-        for (x, y) in visited {
-            for (dir_x, dir_y) in directions {
-                let next_x = x + dir_x;
-                let next_y = y + dir_y;
-                let next_position = (next_x, next_y);
-                let adjusted_x = next_x.rem_euclid(width) as usize;
-                let adjusted_y = next_y.rem_euclid(height) as usize;
+        for (dir_x, dir_y) in directions {
+            let next_position = (x + dir_x, y + dir_y);
+            let adjusted_x = next_position.0.rem_euclid(width) as usize;
+            let adjusted_y = next_position.1.rem_euclid(height) as usize;
 
-                if new_visited.contains(&next_position) || map[adjusted_y][adjusted_x] == '#' {
-                    continue;
-                }
-
-                new_visited.insert(next_position);
+            if map[adjusted_y][adjusted_x] != '#' && !distances.contains_key(&next_position) {
+                distances.insert(next_position, distance + 1);
+                queue.push_back(next_position);
             }
         }
-
-        visited = new_visited;
-        // println!("{}", visited.len());
     }
 
-    visited.len()
+    distances
+        .values()
+        .filter(|&&distance| distance % 2 == steps % 2)
+        .count()
 }
 
-fn parse(lines: &Vec<String>) -> Vec<Vec<char>> {
+fn parse(lines: &[String]) -> Vec<Vec<char>> {
     lines.iter().map(|s| s.chars().collect()).collect()
 }
 
-fn position(map: &Vec<Vec<char>>, char: char) -> (isize, isize) {
+fn position(map: &[Vec<char>], char: char) -> (isize, isize) {
     let mut position = (0, 0);
     for (y, row) in map.iter().enumerate() {
         if let Some(x) = row.iter().position(|&c| c == char) {
@@ -129,8 +158,7 @@ fn position(map: &Vec<Vec<char>>, char: char) -> (isize, isize) {
 mod tests {
     use super::*;
 
-    #[test]
-    fn sample_input_part_1() {
+    fn sample_map() -> Vec<Vec<char>> {
         let lines = vec![
             "...........",
             ".....###.#.",
@@ -144,34 +172,58 @@ mod tests {
             ".##..##.##.",
             "...........",
         ];
-        let lines: Vec<String> = lines.into_iter().map(|s| s.parse().unwrap()).collect();
-        let map = parse(&lines);
+        let lines: Vec<String> = lines.into_iter().map(|s| s.to_string()).collect();
+        parse(&lines)
+    }
 
-        let result = plots_after_steps(&map, 6);
+    #[test]
+    fn sample_input_part_1() {
+        let result = plots_after_steps(&sample_map(), 6);
 
         assert_eq!(result, 16);
     }
 
-    // #[test]
-    // fn sample_input_part_2() {
-    //     let lines = vec![
-    //         "...........",
-    //         ".....###.#.",
-    //         ".###.##..#.",
-    //         "..#.#...#..",
-    //         "....#.#....",
-    //         ".##..S####.",
-    //         ".##..#...#.",
-    //         ".......##..",
-    //         ".##.#.####.",
-    //         ".##..##.##.",
-    //         "...........",
-    //     ];
-    //     let lines: Vec<String> = lines.into_iter().map(|s| s.parse().unwrap()).collect();
-    //     let map = parse(&lines);
-
-    //     let result = infinite_plots_after_steps(&map, 100);
-
-    //     assert_eq!(result, 6536);
-    // }
+    #[test]
+    fn sample_input_part_2() {
+        let map = sample_map();
+
+        assert_eq!(infinite_plots_after_steps(&map, 6), 16);
+        assert_eq!(infinite_plots_after_steps(&map, 10), 50);
+        assert_eq!(infinite_plots_after_steps(&map, 50), 1594);
+        assert_eq!(infinite_plots_after_steps(&map, 100), 6536);
+    }
+
+    #[test]
+    fn input_validation() {
+        assert_eq!(
+            plots_after_any_steps(&sample_map(), 5000),
+            Err(GardenError::BlockedStartLines)
+        );
+
+        let lines: Vec<String> = vec!["...".to_string(), "S..".to_string(), "...".to_string()];
+        assert_eq!(
+            plots_after_any_steps(&parse(&lines), 10),
+            Err(GardenError::StartNotCentered)
+        );
+
+        let lines: Vec<String> = vec!["..".to_string(), "S.".to_string()];
+        assert_eq!(
+            plots_after_any_steps(&parse(&lines), 10),
+            Err(GardenError::NotSquare)
+        );
+    }
+
+    #[test]
+    fn fitted_quadratic() {
+        let lines = vec![".....", ".#.#.", "..S..", ".#.#.", "....."];
+        let lines: Vec<String> = lines.into_iter().map(|s| s.to_string()).collect();
+        let map = parse(&lines);
+
+        for steps in [7, 32, 53, 104] {
+            assert_eq!(
+                plots_after_any_steps(&map, steps),
+                Ok(infinite_plots_after_steps(&map, steps))
+            );
+        }
+    }
 }
@@ -1,10 +1,12 @@
 use std::str::FromStr;
 
 use itertools::Itertools;
+use num_rational::BigRational;
+use num_traits::{ToPrimitive, Zero};
 
 use crate::parser;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Hailstone {
     position: (isize, isize, isize),
     velocity: (isize, isize, isize),
@@ -20,46 +22,13 @@ impl Hailstone {
         (x_1, y_1, x_2, y_2)
     }
 
-    fn elements(&self) -> (f64, f64, f64, f64, f64, f64) {
-        let (x, y, z) = self.position;
-        let (vx, vy, vz) = self.velocity;
-
-        (
-            x as f64, y as f64, z as f64, vx as f64, vy as f64, vz as f64,
-        )
-    }
-
     fn in_the_future(&self, (x, y): (f64, f64)) -> bool {
         let (sx, sy, _, _) = self.points_2d();
 
-        if self.velocity.0 > 0 && x < sx {
-            return false;
-        } else if self.velocity.0 < 0 && x > sx {
-            return false;
-        }
-
-        if self.velocity.1 > 0 && y < sy {
-            return false;
-        } else if self.velocity.1 < 0 && y > sy {
-            return false;
-        }
-
-        true
-    }
+        let behind_x = (self.velocity.0 > 0 && x < sx) || (self.velocity.0 < 0 && x > sx);
+        let behind_y = (self.velocity.1 > 0 && y < sy) || (self.velocity.1 < 0 && y > sy);
 
-    fn relative_to(&self, other: &Hailstone) -> Hailstone {
-        Hailstone {
-            position: (
-                self.position.0 - other.position.0,
-                self.position.1 - other.position.1,
-                self.position.2 - other.position.2,
-            ),
-            velocity: (
-                self.velocity.0 - other.velocity.0,
-                self.velocity.1 - other.velocity.1,
-                self.velocity.2 - other.velocity.2,
-            ),
-        }
+        !behind_x && !behind_y
     }
 }
 
@@ -93,15 +62,15 @@ pub fn part1() -> usize {
 
 pub fn part2() -> usize {
     let hailstones: Vec<Hailstone> = parser::read("data/day24.txt").unwrap();
-    sum_of_perfect_throw(&hailstones)
+    sum_of_perfect_throw(&hailstones).unwrap()
 }
 
-fn count_intersections(hailstones: &Vec<Hailstone>, min: f64, max: f64) -> usize {
+fn count_intersections(hailstones: &[Hailstone], min: f64, max: f64) -> usize {
     hailstones
-        .into_iter()
+        .iter()
         .combinations(2)
         .filter(|hs| {
-            let hail_a = hs.get(0).unwrap();
+            let hail_a = hs.first().unwrap();
             let hail_b = hs.get(1).unwrap();
             let intersection = intersection_2d(hail_a, hail_b);
 
@@ -136,42 +105,187 @@ fn intersection_2d(hail_a: &Hailstone, hail_b: &Hailstone) -> Option<(f64, f64)>
     Some((x, y))
 }
 
-fn sum_of_perfect_throw(hailstones: &Vec<Hailstone>) -> usize {
-    let hail_0 = hailstones.get(3).unwrap();
-    let hail_1 = hailstones.get(164).unwrap();
-    let hail_2 = hailstones.get(199).unwrap();
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ThrowError {
+    NoIndependentHailstones,
+    NotInteger,
+    Misses(usize),
+    NegativeSum(isize),
+}
+
+fn sum_of_perfect_throw(hailstones: &[Hailstone]) -> Result<usize, ThrowError> {
+    let rock = perfect_throw(hailstones)?;
+    let sum = rock.position.0 + rock.position.1 + rock.position.2;
+    usize::try_from(sum).map_err(|_| ThrowError::NegativeSum(sum))
+}
+
+// The rock hits hailstone i when (P - p_i) x (V - v_i) = 0. The P x V term is the same
+// for every hailstone, so subtracting two of these equations leaves 3 linear ones
+fn perfect_throw(hailstones: &[Hailstone]) -> Result<Hailstone, ThrowError> {
+    let solution = hailstones
+        .iter()
+        .combinations(3)
+        .filter(|triple| {
+            let velocities = triple
+                .iter()
+                .map(|hail| vector(hail.velocity))
+                .collect_vec();
+            !determinant(&velocities).is_zero()
+        })
+        .find_map(|triple| {
+            let mut system = pair_equations(triple[0], triple[1]);
+            system.extend(pair_equations(triple[0], triple[2]));
+            solve(system)
+        })
+        .ok_or(ThrowError::NoIndependentHailstones)?;
+
+    let integers: Vec<isize> = solution
+        .iter()
+        .map(|value| value.to_integer().to_isize().filter(|_| value.is_integer()))
+        .collect::<Option<_>>()
+        .ok_or(ThrowError::NotInteger)?;
+    let rock = Hailstone {
+        position: (integers[0], integers[1], integers[2]),
+        velocity: (integers[3], integers[4], integers[5]),
+    };
+
+    match hailstones.iter().position(|hail| !hits(&rock, hail)) {
+        Some(index) => Err(ThrowError::Misses(index)),
+        None => Ok(rock),
+    }
+}
+
+fn vector((x, y, z): (isize, isize, isize)) -> Vec<BigRational> {
+    [x, y, z]
+        .into_iter()
+        .map(|n| BigRational::from_integer(n.into()))
+        .collect()
+}
+
+fn determinant(m: &[Vec<BigRational>]) -> BigRational {
+    &m[0][0] * (&m[1][1] * &m[2][2] - &m[1][2] * &m[2][1])
+        - &m[0][1] * (&m[1][0] * &m[2][2] - &m[1][2] * &m[2][0])
+        + &m[0][2] * (&m[1][0] * &m[2][1] - &m[1][1] * &m[2][0])
+}
 
-    let (x1, y1, z1, vx1, vy1, vz1) = hail_1.elements();
-    let (xr1, yr1, zr1, vxr1, _, vzr1) = hail_1.relative_to(hail_0).elements();
-    let (x2, y2, z2, vx2, vy2, vz2) = hail_2.elements();
-    let (xr2, yr2, zr2, vxr2, _, vzr2) = hail_2.relative_to(hail_0).elements();
+fn cross(a: &[BigRational], b: &[BigRational]) -> Vec<BigRational> {
+    vec![
+        &a[1] * &b[2] - &a[2] * &b[1],
+        &a[2] * &b[0] - &a[0] * &b[2],
+        &a[0] * &b[1] - &a[1] * &b[0],
+    ]
+}
 
-    let numerator =
-        (yr2 * xr1 * vzr1) - (vxr1 * yr2 * zr1) + (yr1 * zr2 * vxr1) - (yr1 * xr2 * vzr1);
-    let denominator = yr1 * ((vzr1 * vxr2) - (vxr1 * vzr2));
-    let t2 = numerator / denominator;
+// Rows of [a]x, the matrix such that [a]x * b = a x b
+fn cross_matrix(a: &[BigRational]) -> Vec<Vec<BigRational>> {
+    let zero = BigRational::zero();
+    vec![
+        vec![zero.clone(), -a[2].clone(), a[1].clone()],
+        vec![a[2].clone(), zero.clone(), -a[0].clone()],
+        vec![-a[1].clone(), a[0].clone(), zero],
+    ]
+}
 
-    let numerator = (yr1 * xr2) + (yr1 * vxr2 * t2) - (yr2 * xr1);
-    let denominator = yr2 * vxr1;
-    let t1 = numerator / denominator;
+// P x (v_i - v_j) + (p_i - p_j) x V = p_i x v_i - p_j x v_j, as augmented rows over (P, V)
+fn pair_equations(hail_i: &Hailstone, hail_j: &Hailstone) -> Vec<Vec<BigRational>> {
+    let (p_i, v_i) = (vector(hail_i.position), vector(hail_i.velocity));
+    let (p_j, v_j) = (vector(hail_j.position), vector(hail_j.velocity));
+    let dv: Vec<BigRational> = v_i.iter().zip(&v_j).map(|(a, b)| a - b).collect();
+    let dp: Vec<BigRational> = p_i.iter().zip(&p_j).map(|(a, b)| a - b).collect();
 
-    let cx1 = x1 + (t1 * vx1);
-    let cy1 = y1 + (t1 * vy1);
-    let cz1 = z1 + (t1 * vz1);
+    let rhs: Vec<BigRational> = cross(&p_i, &v_i)
+        .iter()
+        .zip(cross(&p_j, &v_j))
+        .map(|(a, b)| a - b)
+        .collect();
 
-    let cx2 = x2 + (t2 * vx2);
-    let cy2 = y2 + (t2 * vy2);
-    let cz2 = z2 + (t2 * vz2);
+    cross_matrix(&dv)
+        .into_iter()
+        .zip(cross_matrix(&dp))
+        .zip(rhs)
+        .map(|((p_row, v_row), rhs)| {
+            p_row
+                .into_iter()
+                .map(|a| -a)
+                .chain(v_row)
+                .chain([rhs])
+                .collect()
+        })
+        .collect()
+}
+
+// Gauss-Jordan elimination on an augmented matrix, None when it is singular
+fn solve(mut system: Vec<Vec<BigRational>>) -> Option<Vec<BigRational>> {
+    let size = system.len();
 
-    let xm = (cx2 - cx1) / (t2 - t1);
-    let ym = (cy2 - cy1) / (t2 - t1);
-    let zm = (cz2 - cz1) / (t2 - t1);
+    for column in 0..size {
+        let pivot = (column..size).find(|&row| !system[row][column].is_zero())?;
+        system.swap(column, pivot);
+
+        let pivot_row = system[column].clone();
+        for (row, equation) in system.iter_mut().enumerate() {
+            if row != column && !equation[column].is_zero() {
+                let factor = &equation[column] / &pivot_row[column];
+                for (value, pivot_value) in equation.iter_mut().zip(&pivot_row) {
+                    *value -= &factor * pivot_value;
+                }
+            }
+        }
+    }
 
-    let xc = cx1 - (xm * t1);
-    let yc = cy1 - (ym * t1);
-    let zc = cz1 - (zm * t1);
+    Some(
+        system
+            .iter()
+            .enumerate()
+            .map(|(row, equation)| &equation[size] / &equation[row])
+            .collect(),
+    )
+}
 
-    (xc + yc + zc) as usize
+// Whether both meet at the same non-negative integer time on every axis
+fn hits(rock: &Hailstone, hail: &Hailstone) -> bool {
+    let axes = [
+        (
+            rock.position.0,
+            rock.velocity.0,
+            hail.position.0,
+            hail.velocity.0,
+        ),
+        (
+            rock.position.1,
+            rock.velocity.1,
+            hail.position.1,
+            hail.velocity.1,
+        ),
+        (
+            rock.position.2,
+            rock.velocity.2,
+            hail.position.2,
+            hail.velocity.2,
+        ),
+    ];
+
+    let mut time = None;
+    for (rock_position, rock_velocity, hail_position, hail_velocity) in axes {
+        let (distance, closing_speed) = (
+            hail_position as i128 - rock_position as i128,
+            rock_velocity as i128 - hail_velocity as i128,
+        );
+        if closing_speed == 0 {
+            if distance != 0 {
+                return false;
+            }
+            continue;
+        }
+        if distance % closing_speed != 0 || distance / closing_speed < 0 {
+            return false;
+        }
+        if time.is_some_and(|time| time != distance / closing_speed) {
+            return false;
+        }
+        time = Some(distance / closing_speed);
+    }
+    true
 }
 
 #[cfg(test)]
@@ -194,19 +308,66 @@ mod tests {
         assert_eq!(result, 2);
     }
 
+    fn sample_hailstones() -> Vec<Hailstone> {
+        let lines = vec![
+            "19, 13, 30 @ -2,  1, -2",
+            "18, 19, 22 @ -1, -1, -2",
+            "20, 25, 34 @ -2, -2, -4",
+            "12, 31, 28 @ -1, -2, -1",
+            "20, 19, 15 @  1, -5, -3",
+        ];
+        lines.into_iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn rock_position_and_velocity() {
+        let rock = perfect_throw(&sample_hailstones()).unwrap();
+
+        assert_eq!(
+            rock,
+            Hailstone {
+                position: (24, 13, 10),
+                velocity: (-3, 1, 2)
+            }
+        );
+        assert!(hits(&rock, &sample_hailstones()[0]));
+    }
+
+    #[test]
+    fn impossible_throws() {
+        let mut hailstones = sample_hailstones();
+        hailstones.push("0, 0, 0 @ 1, 1, 1".parse().unwrap());
+        assert_eq!(perfect_throw(&hailstones), Err(ThrowError::Misses(5)));
+
+        let parallel: Vec<Hailstone> = [
+            "0, 0, 0 @ 1, 0, 0",
+            "0, 1, 0 @ 2, 0, 0",
+            "0, 0, 1 @ 3, 0, 0",
+        ]
+        .into_iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+        assert_eq!(
+            perfect_throw(&parallel),
+            Err(ThrowError::NoIndependentHailstones)
+        );
+    }
+
     #[test]
     fn sample_input_part_2() {
-        // let lines = vec![
-        //     "19, 13, 30 @ -2,  1, -2",
-        //     "18, 19, 22 @ -1, -1, -2",
-        //     "20, 25, 34 @ -2, -2, -4",
-        //     "12, 31, 28 @ -1, -2, -1",
-        //     "20, 19, 15 @  1, -5, -3",
-        // ];
-        // let hailstones: Vec<Hailstone> = lines.into_iter().map(|s| s.parse().unwrap()).collect();
-
-        // let result = sum_of_perfect_throw(&hailstones);
-
-        // assert_eq!(result, 47);
+        assert_eq!(sum_of_perfect_throw(&sample_hailstones()), Ok(47));
+
+        // The same throw seen from the other side of every axis
+        let mirrored: Vec<Hailstone> = sample_hailstones()
+            .into_iter()
+            .map(|hail| Hailstone {
+                position: (-hail.position.0, -hail.position.1, -hail.position.2),
+                velocity: (-hail.velocity.0, -hail.velocity.1, -hail.velocity.2),
+            })
+            .collect();
+        assert_eq!(
+            sum_of_perfect_throw(&mirrored),
+            Err(ThrowError::NegativeSum(-47))
+        );
     }
 }
@@ -1,6 +1,5 @@
 mod graph;
 
-use rand::prelude::*;
 use std::str::FromStr;
 
 use graph::Graph;
@@ -41,42 +40,29 @@ pub fn part1() -> usize {
 }
 
 pub fn part2() -> usize {
-    0
+    let connections: Vec<Connection> = parser::read("data/day25.txt").unwrap();
+    cut_weight(&connections)
 }
 
-fn disconnect_and_sum(connections: &Vec<Connection>) -> usize {
+fn graph(connections: &[Connection]) -> Graph<'_> {
     let pairs: Vec<(&str, &str)> = connections.iter().flat_map(Connection::to_pairs).collect();
-    let graph = Graph::from_pairs(pairs);
-
-    loop {
-        let mut candidate = graph.clone();
-        krager_min_cut(&mut candidate);
-
-        if candidate.weights.values().all(|weight| *weight == 3) {
-            return candidate
-                .nodes
-                .values()
-                .fold(1, |acc, node| acc * node.count);
-        }
-    }
+    Graph::from_pairs(pairs)
 }
 
-fn krager_min_cut(graph: &mut Graph) {
-    let mut rng = rand::rng();
+fn disconnect_and_sum(connections: &[Connection]) -> usize {
+    let cut = graph(connections).min_cut().unwrap();
+    cut.partitions.0.len() * cut.partitions.1.len()
+}
 
-    while graph.nodes.len() > 2 {
-        let a = graph.nodes.keys().choose(&mut rng).unwrap();
-        let b = graph.edges.get(a).unwrap().iter().choose(&mut rng).unwrap();
-        graph.contract_nodes(a, b);
-    }
+fn cut_weight(connections: &[Connection]) -> usize {
+    graph(connections).min_cut().unwrap().weight
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn sample_input_part_1() {
+    fn sample_connections() -> Vec<Connection> {
         let lines = vec![
             "jqt: rhn xhk nvd",
             "rsh: frs pzl lsr",
@@ -92,13 +78,31 @@ mod tests {
             "rzs: qnr cmg lsr rsh",
             "frs: qnr lhk lsr",
         ];
-        let connections: Vec<Connection> = lines.into_iter().map(|s| s.parse().unwrap()).collect();
+        lines.into_iter().map(|s| s.parse().unwrap()).collect()
+    }
 
-        let result = disconnect_and_sum(&connections);
+    #[test]
+    fn cut_wires() {
+        let connections = sample_connections();
+        let cut = graph(&connections).min_cut().unwrap();
+
+        assert_eq!(cut.weight, 3);
+        assert_eq!(
+            cut.edges,
+            vec![("bvb", "cmg"), ("hfx", "pzl"), ("jqt", "nvd")]
+        );
+        assert_eq!(cut.partitions.0.len() + cut.partitions.1.len(), 15);
+    }
+
+    #[test]
+    fn sample_input_part_1() {
+        let result = disconnect_and_sum(&sample_connections());
 
         assert_eq!(result, 54);
     }
 
     #[test]
-    fn sample_input_part_2() {}
+    fn sample_input_part_2() {
+        assert_eq!(cut_weight(&sample_connections()), 3);
+    }
 }
@@ -1,4 +1,7 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Node<'a> {
@@ -6,6 +9,13 @@ pub struct Node<'a> {
     pub count: usize,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinCut<'a> {
+    pub weight: usize,
+    pub edges: Vec<(&'a str, &'a str)>,
+    pub partitions: (Vec<&'a str>, Vec<&'a str>),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Graph<'a> {
     pub nodes: HashMap<&'a str, Node<'a>>,
@@ -57,7 +67,7 @@ impl<'a> Graph<'a> {
                     node.insert(a);
                 });
 
-                let b_c_weight = self.weights.get(&(b, c)).unwrap().clone();
+                let b_c_weight = self.weights[&(b, c)];
                 *self.weights.entry((a, c)).or_insert(0) += b_c_weight;
                 *self.weights.entry((c, a)).or_insert(0) += b_c_weight;
 
@@ -69,6 +79,81 @@ impl<'a> Graph<'a> {
         self.weights.remove(&(a, b));
         self.weights.remove(&(b, a));
     }
+
+    // Stoer-Wagner: every phase finds the cut separating the last two nodes of a maximum
+    // adjacency ordering, then merges them. The lightest of those cuts is a global minimum
+    pub fn min_cut(&self) -> Option<MinCut<'a>> {
+        let mut graph = self.clone();
+        let mut members: HashMap<&'a str, Vec<&'a str>> = self
+            .nodes
+            .keys()
+            .map(|&label| (label, vec![label]))
+            .collect();
+        let mut best: Option<(usize, Vec<&'a str>)> = None;
+
+        while graph.nodes.len() > 1 {
+            let (s, t, cut_weight) = graph.maximum_adjacency_phase();
+            if best.as_ref().is_none_or(|(weight, _)| cut_weight < *weight) {
+                best = Some((cut_weight, members[t].clone()));
+            }
+
+            let merged = members.remove(t).unwrap();
+            members.get_mut(s).unwrap().extend(merged);
+            graph.contract_nodes(s, t);
+        }
+
+        let (weight, mut side) = best?;
+        side.sort();
+        let in_side: HashSet<&str> = side.iter().copied().collect();
+        let mut other: Vec<&'a str> = self
+            .nodes
+            .keys()
+            .copied()
+            .filter(|label| !in_side.contains(label))
+            .collect();
+        other.sort();
+
+        let mut edges: Vec<(&'a str, &'a str)> = self
+            .edges
+            .iter()
+            .flat_map(|(&a, others)| others.iter().map(move |&b| (a, b)))
+            .filter(|(a, b)| a < b && in_side.contains(a) != in_side.contains(b))
+            .collect();
+        edges.sort();
+
+        Some(MinCut {
+            weight,
+            edges,
+            partitions: (side, other),
+        })
+    }
+
+    // Returns the last two nodes added and the weight between the last one and the rest
+    fn maximum_adjacency_phase(&self) -> (&'a str, &'a str, usize) {
+        let mut added: HashSet<&'a str> = HashSet::new();
+        let mut connectivity: HashMap<&'a str, usize> = HashMap::new();
+        // Every node starts at zero so disconnected ones are still reached, smallest label first
+        let mut queue: BinaryHeap<(usize, Reverse<&'a str>)> =
+            self.nodes.keys().map(|&node| (0, Reverse(node))).collect();
+        let (mut previous, mut last, mut last_weight) = ("", "", 0);
+
+        while let Some((weight, Reverse(node))) = queue.pop() {
+            if !added.insert(node) {
+                continue;
+            }
+            (previous, last, last_weight) = (last, node, weight);
+
+            for &neighbour in &self.edges[node] {
+                if !added.contains(neighbour) {
+                    let connection = connectivity.entry(neighbour).or_insert(0);
+                    *connection += self.weights[&(node, neighbour)];
+                    queue.push((*connection, Reverse(neighbour)));
+                }
+            }
+        }
+
+        (previous, last, last_weight)
+    }
 }
 
 #[cfg(test)]
@@ -139,4 +224,39 @@ mod tests {
 
         assert_eq!(graph, expected);
     }
+
+    #[test]
+    fn min_cut() {
+        let pairs = vec![
+            ("a", "b"),
+            ("a", "c"),
+            ("b", "c"),
+            ("c", "d"),
+            ("d", "e"),
+            ("d", "f"),
+            ("e", "f"),
+        ];
+
+        let cut = Graph::from_pairs(pairs).min_cut().unwrap();
+
+        assert_eq!(
+            cut,
+            MinCut {
+                weight: 1,
+                edges: vec![("c", "d")],
+                partitions: (vec!["d", "e", "f"], vec!["a", "b", "c"]),
+            }
+        );
+    }
+
+    #[test]
+    fn min_cut_of_disconnected_graph() {
+        let cut = Graph::from_pairs(vec![("a", "b"), ("c", "d")])
+            .min_cut()
+            .unwrap();
+
+        assert_eq!(cut.weight, 0);
+        assert!(cut.edges.is_empty());
+        assert_eq!(cut.partitions.0.len() + cut.partitions.1.len(), 4);
+    }
 }
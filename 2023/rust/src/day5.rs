@@ -1,4 +1,6 @@
-use itertools::Itertools;
+mod piecewise;
+
+use piecewise::{PiecewiseMap, RangeSet};
 
 use crate::parser;
 
@@ -16,83 +18,48 @@ pub fn part2() -> usize {
     lowest_location_with_range(&seeds, &all_mappings)
 }
 
-fn lowest_location(seeds: &Vec<usize>, all_mappings: &Vec<Vec<Mapping>>) -> usize {
-    let mut seed_ranges = Vec::new();
-
-    for seed in seeds {
-        seed_ranges.push(*seed);
-        seed_ranges.push(1);
-    }
-
-    lowest_location_with_range(&seed_ranges, all_mappings)
+fn lowest_location(seeds: &[usize], all_mappings: &[Vec<Mapping>]) -> usize {
+    let seeds = RangeSet::new(seeds.iter().map(|&seed| (seed, seed)).collect());
+    almanac(all_mappings).image(&seeds).min().unwrap()
 }
 
-fn lowest_location_with_range(seed_ranges: &Vec<usize>, all_mappings: &Vec<Vec<Mapping>>) -> usize {
-    let mut ranges = Vec::new();
-
-    for chunk in seed_ranges.chunks(2) {
-        let start = chunk[0];
-        let length = chunk[1];
-        ranges.push((start, start + length - 1));
-    }
-
-    let mut all_sorted_mappings = Vec::new();
-    for mappings in all_mappings {
-        let sorted_mappings: Vec<Mapping> = mappings
-            .clone()
-            .into_iter()
-            .sorted_by_key(|(_, src, _)| *src)
-            .collect();
-
-        let mut last_end = 0;
-        let mut new_sorted_mappings = Vec::new();
-        for mapping @ (_, src, length) in sorted_mappings {
-            if src > last_end {
-                new_sorted_mappings.push((last_end, last_end, src - last_end));
-            }
-
-            new_sorted_mappings.push(mapping);
-            last_end = src + length;
-        }
-        new_sorted_mappings.push((last_end, last_end, usize::MAX - last_end));
-
-        all_sorted_mappings.push(new_sorted_mappings);
-    }
-
-    lowest_with_ranges(&ranges, &all_sorted_mappings)
+fn lowest_location_with_range(seed_ranges: &[usize], all_mappings: &[Vec<Mapping>]) -> usize {
+    almanac(all_mappings)
+        .image(&seed_range_set(seed_ranges))
+        .min()
+        .unwrap()
 }
 
-fn lowest_with_ranges(ranges: &Vec<(usize, usize)>, all_mappings: &Vec<Vec<Mapping>>) -> usize {
-    if all_mappings.is_empty() {
-        return *ranges.iter().map(|(low, _)| low).min().unwrap();
-    }
-
-    let (mappings, rest) = all_mappings.split_at(1);
-    let mappings = &mappings[0];
-    let rest = rest.to_vec();
-    let mut new_ranges = Vec::new();
-
-    for (range_start, range_end) in ranges {
-        for (dst, src, length) in mappings {
-            let mapping_start = src;
-            let mapping_end = src + length - 1;
-
-            if *range_start <= mapping_end && range_end >= mapping_start {
-                let overlap_start = range_start.max(mapping_start);
-                let overlap_end = range_end.min(&mapping_end);
-
-                let new_range_start = overlap_start - mapping_start + dst;
-                let new_range_end = overlap_end - mapping_start + dst;
+// Every map from seed to location composed into a single one
+fn almanac(all_mappings: &[Vec<Mapping>]) -> PiecewiseMap {
+    all_mappings
+        .iter()
+        .fold(PiecewiseMap::identity(), |composed, mappings| {
+            composed.then(&PiecewiseMap::from_mappings(mappings))
+        })
+}
 
-                new_ranges.push((new_range_start, new_range_end));
-            }
-        }
-    }
+fn seed_range_set(seed_ranges: &[usize]) -> RangeSet {
+    RangeSet::new(
+        seed_ranges
+            .chunks(2)
+            .filter(|chunk| chunk[1] > 0)
+            .map(|chunk| (chunk[0], chunk[0] + chunk[1] - 1))
+            .collect(),
+    )
+}
 
-    lowest_with_ranges(&new_ranges, &rest)
+// The planted seeds that end up at the given location
+#[allow(dead_code)]
+fn seeds_at_location(almanac: &PiecewiseMap, seeds: &RangeSet, location: usize) -> Vec<usize> {
+    almanac
+        .inverse(location)
+        .into_iter()
+        .filter(|&seed| seeds.contains(seed))
+        .collect()
 }
 
-fn parse(lines: &Vec<String>) -> (Vec<usize>, Vec<Vec<Mapping>>) {
+fn parse(lines: &[String]) -> (Vec<usize>, Vec<Vec<Mapping>>) {
     let mut parts = lines.split(String::is_empty);
 
     let seeds = parts
@@ -125,44 +92,45 @@ fn parse(lines: &Vec<String>) -> (Vec<usize>, Vec<Vec<Mapping>>) {
 mod tests {
     use super::*;
 
+    const SAMPLE: [&str; 33] = [
+        "seeds: 79 14 55 13",
+        "",
+        "seed-to-soil map:",
+        "50 98 2",
+        "52 50 48",
+        "",
+        "soil-to-fertilizer map:",
+        "0 15 37",
+        "37 52 2",
+        "39 0 15",
+        "",
+        "fertilizer-to-water map:",
+        "49 53 8",
+        "0 11 42",
+        "42 0 7",
+        "57 7 4",
+        "",
+        "water-to-light map:",
+        "88 18 7",
+        "18 25 70",
+        "",
+        "light-to-temperature map:",
+        "45 77 23",
+        "81 45 19",
+        "68 64 13",
+        "",
+        "temperature-to-humidity map:",
+        "0 69 1",
+        "1 0 69",
+        "",
+        "humidity-to-location map:",
+        "60 56 37",
+        "56 93 4",
+    ];
+
     #[test]
     fn sample_input_part_1() {
-        let lines = vec![
-            "seeds: 79 14 55 13",
-            "",
-            "seed-to-soil map:",
-            "50 98 2",
-            "52 50 48",
-            "",
-            "soil-to-fertilizer map:",
-            "0 15 37",
-            "37 52 2",
-            "39 0 15",
-            "",
-            "fertilizer-to-water map:",
-            "49 53 8",
-            "0 11 42",
-            "42 0 7",
-            "57 7 4",
-            "",
-            "water-to-light map:",
-            "88 18 7",
-            "18 25 70",
-            "",
-            "light-to-temperature map:",
-            "45 77 23",
-            "81 45 19",
-            "68 64 13",
-            "",
-            "temperature-to-humidity map:",
-            "0 69 1",
-            "1 0 69",
-            "",
-            "humidity-to-location map:",
-            "60 56 37",
-            "56 93 4",
-        ];
-        let lines: Vec<String> = lines.into_iter().map(|s| s.parse().unwrap()).collect();
+        let lines: Vec<String> = SAMPLE.iter().map(|s| s.to_string()).collect();
         let (seeds, all_mappings) = parse(&lines);
 
         let result = lowest_location(&seeds, &all_mappings);
@@ -172,46 +140,38 @@ mod tests {
 
     #[test]
     fn sample_input_part_2() {
-        let lines = vec![
-            "seeds: 79 14 55 13",
-            "",
-            "seed-to-soil map:",
-            "50 98 2",
-            "52 50 48",
-            "",
-            "soil-to-fertilizer map:",
-            "0 15 37",
-            "37 52 2",
-            "39 0 15",
-            "",
-            "fertilizer-to-water map:",
-            "49 53 8",
-            "0 11 42",
-            "42 0 7",
-            "57 7 4",
-            "",
-            "water-to-light map:",
-            "88 18 7",
-            "18 25 70",
-            "",
-            "light-to-temperature map:",
-            "45 77 23",
-            "81 45 19",
-            "68 64 13",
-            "",
-            "temperature-to-humidity map:",
-            "0 69 1",
-            "1 0 69",
-            "",
-            "humidity-to-location map:",
-            "60 56 37",
-            "56 93 4",
-        ];
-        let lines: Vec<String> = lines.into_iter().map(|s| s.parse().unwrap()).collect();
+        let lines: Vec<String> = SAMPLE.iter().map(|s| s.to_string()).collect();
         let (seeds, all_mappings) = parse(&lines);
 
         let result = lowest_location_with_range(&seeds, &all_mappings);
 
         assert_eq!(result, 46)
     }
+
+    #[test]
+    fn composed_almanac() {
+        let lines: Vec<String> = SAMPLE.iter().map(|s| s.to_string()).collect();
+        let (seeds, all_mappings) = parse(&lines);
+        let almanac = almanac(&all_mappings);
+
+        assert_eq!(
+            seeds
+                .iter()
+                .map(|&seed| almanac.apply(seed))
+                .collect::<Vec<usize>>(),
+            vec![82, 43, 86, 35]
+        );
+        assert_eq!(almanac.inverse(35), vec![13]);
+        assert_eq!(
+            seeds_at_location(&almanac, &seed_range_set(&seeds), 46),
+            vec![82]
+        );
+
+        let locations = RangeSet::new(vec![(46, 46)]);
+        assert!(almanac.preimage(&locations).contains(82));
+        assert_eq!(
+            almanac.table().lines().next(),
+            Some("0..=13 -> 22..=35 (+22)")
+        );
+    }
 }
@@ -0,0 +1,285 @@
+// Sorted, disjoint and non-adjacent inclusive ranges
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeSet {
+    ranges: Vec<(usize, usize)>,
+}
+
+impl RangeSet {
+    pub fn new(mut ranges: Vec<(usize, usize)>) -> Self {
+        ranges.sort();
+
+        let mut merged: Vec<(usize, usize)> = Vec::new();
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        RangeSet { ranges: merged }
+    }
+
+    pub fn ranges(&self) -> &[(usize, usize)] {
+        &self.ranges
+    }
+
+    pub fn min(&self) -> Option<usize> {
+        self.ranges.first().map(|&(start, _)| start)
+    }
+
+    pub fn contains(&self, value: usize) -> bool {
+        self.ranges
+            .iter()
+            .any(|&(start, end)| start <= value && value <= end)
+    }
+
+    #[allow(dead_code)]
+    pub fn intersection(&self, other: &RangeSet) -> RangeSet {
+        let mut ranges = Vec::new();
+        for &(start, end) in &self.ranges {
+            for &(other_start, other_end) in &other.ranges {
+                if start.max(other_start) <= end.min(other_end) {
+                    ranges.push((start.max(other_start), end.min(other_end)));
+                }
+            }
+        }
+        RangeSet::new(ranges)
+    }
+}
+
+// Values in start..=end are moved by offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Piece {
+    start: usize,
+    end: usize,
+    offset: i128,
+}
+
+impl Piece {
+    fn image(&self) -> (usize, usize) {
+        (shift(self.start, self.offset), shift(self.end, self.offset))
+    }
+}
+
+fn shift(value: usize, offset: i128) -> usize {
+    (value as i128 + offset) as usize
+}
+
+// A function over every usize, stored as sorted pieces with no gaps between them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PiecewiseMap {
+    pieces: Vec<Piece>,
+}
+
+impl PiecewiseMap {
+    pub fn identity() -> Self {
+        PiecewiseMap {
+            pieces: vec![Piece {
+                start: 0,
+                end: usize::MAX,
+                offset: 0,
+            }],
+        }
+    }
+
+    // Mappings are (destination, source, length), values outside every source stay as they are
+    pub fn from_mappings(mappings: &[(usize, usize, usize)]) -> Self {
+        // Empty mappings move nothing and have no inclusive end
+        let mut sorted: Vec<(usize, usize, usize)> = mappings
+            .iter()
+            .copied()
+            .filter(|&(_, _, length)| length > 0)
+            .collect();
+        sorted.sort_by_key(|&(_, src, _)| src);
+
+        let mut pieces = Vec::new();
+        let mut next_start = 0;
+        for (dst, src, length) in sorted {
+            if src > next_start {
+                pieces.push(Piece {
+                    start: next_start,
+                    end: src - 1,
+                    offset: 0,
+                });
+            }
+            pieces.push(Piece {
+                start: src,
+                end: src + length - 1,
+                offset: dst as i128 - src as i128,
+            });
+            next_start = src + length;
+        }
+        pieces.push(Piece {
+            start: next_start,
+            end: usize::MAX,
+            offset: 0,
+        });
+
+        PiecewiseMap::merged(pieces)
+    }
+
+    fn merged(pieces: Vec<Piece>) -> Self {
+        let mut merged: Vec<Piece> = Vec::new();
+        for piece in pieces {
+            match merged.last_mut() {
+                Some(last) if last.offset == piece.offset => last.end = piece.end,
+                _ => merged.push(piece),
+            }
+        }
+        PiecewiseMap { pieces: merged }
+    }
+
+    // The map that applies self first and then other
+    pub fn then(&self, other: &PiecewiseMap) -> PiecewiseMap {
+        let mut pieces = Vec::new();
+        for piece in &self.pieces {
+            let (image_start, image_end) = piece.image();
+            for next in &other.pieces {
+                let (start, end) = (image_start.max(next.start), image_end.min(next.end));
+                if start <= end {
+                    pieces.push(Piece {
+                        start: shift(start, -piece.offset),
+                        end: shift(end, -piece.offset),
+                        offset: piece.offset + next.offset,
+                    });
+                }
+            }
+        }
+        PiecewiseMap::merged(pieces)
+    }
+
+    #[allow(dead_code)]
+    pub fn apply(&self, value: usize) -> usize {
+        let index = self.pieces.partition_point(|piece| piece.end < value);
+        shift(value, self.pieces[index].offset)
+    }
+
+    // Every value that maps to the given one, in increasing order
+    #[allow(dead_code)]
+    pub fn inverse(&self, value: usize) -> Vec<usize> {
+        self.pieces
+            .iter()
+            .filter_map(|piece| {
+                let source = value as i128 - piece.offset;
+                (piece.start as i128 <= source && source <= piece.end as i128)
+                    .then_some(source as usize)
+            })
+            .collect()
+    }
+
+    pub fn image(&self, ranges: &RangeSet) -> RangeSet {
+        let mut images = Vec::new();
+        for &(start, end) in ranges.ranges() {
+            let first = self.pieces.partition_point(|piece| piece.end < start);
+            for piece in self.pieces[first..]
+                .iter()
+                .take_while(|piece| piece.start <= end)
+            {
+                images.push((
+                    shift(start.max(piece.start), piece.offset),
+                    shift(end.min(piece.end), piece.offset),
+                ));
+            }
+        }
+        RangeSet::new(images)
+    }
+
+    #[allow(dead_code)]
+    pub fn preimage(&self, ranges: &RangeSet) -> RangeSet {
+        let mut sources = Vec::new();
+        for piece in &self.pieces {
+            let (image_start, image_end) = piece.image();
+            for &(start, end) in ranges.ranges() {
+                let (start, end) = (image_start.max(start), image_end.min(end));
+                if start <= end {
+                    sources.push((shift(start, -piece.offset), shift(end, -piece.offset)));
+                }
+            }
+        }
+        RangeSet::new(sources)
+    }
+
+    // One line per piece, "start..=end -> image_start..=image_end (offset)"
+    #[allow(dead_code)]
+    pub fn table(&self) -> String {
+        self.pieces
+            .iter()
+            .map(|piece| {
+                let (image_start, image_end) = piece.image();
+                format!(
+                    "{}..={} -> {}..={} ({:+})",
+                    piece.start, piece.end, image_start, image_end, piece.offset
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_sets_are_normalised() {
+        let set = RangeSet::new(vec![(10, 12), (0, 3), (4, 5), (11, 20)]);
+        assert_eq!(set.ranges(), &[(0, 5), (10, 20)]);
+        assert_eq!(set.min(), Some(0));
+        assert!(set.contains(15) && !set.contains(7));
+
+        let other = RangeSet::new(vec![(3, 11), (20, 30)]);
+        assert_eq!(
+            set.intersection(&other).ranges(),
+            &[(3, 5), (10, 11), (20, 20)]
+        );
+    }
+
+    #[test]
+    fn composition() {
+        let first = PiecewiseMap::from_mappings(&[(10, 0, 5)]);
+        let second = PiecewiseMap::from_mappings(&[(0, 12, 2), (100, 3, 2)]);
+        let composed = first.then(&second);
+
+        for value in 0..20 {
+            assert_eq!(composed.apply(value), second.apply(first.apply(value)));
+        }
+        assert_eq!(
+            composed.table(),
+            [
+                "0..=1 -> 10..=11 (+10)",
+                "2..=3 -> 0..=1 (-2)",
+                "4..=4 -> 14..=14 (+10)",
+                "5..=11 -> 5..=11 (+0)",
+                "12..=13 -> 0..=1 (-12)",
+            ]
+            .join("\n")
+                + &format!("\n14..={} -> 14..={} (+0)", usize::MAX, usize::MAX)
+        );
+    }
+
+    #[test]
+    fn empty_mappings() {
+        let map = PiecewiseMap::from_mappings(&[(5, 0, 0), (10, 0, 2), (7, 3, 0)]);
+
+        assert_eq!(map, PiecewiseMap::from_mappings(&[(10, 0, 2)]));
+        assert_eq!(map.apply(1), 11);
+        assert_eq!(map.apply(3), 3);
+    }
+
+    #[test]
+    fn inverse_lookups() {
+        // 0..=4 moves onto 10..=14, which also keeps its own values
+        let map = PiecewiseMap::from_mappings(&[(10, 0, 5)]);
+
+        assert_eq!(map.inverse(12), vec![2, 12]);
+        assert_eq!(map.inverse(3), Vec::<usize>::new());
+        assert_eq!(
+            map.preimage(&RangeSet::new(vec![(2, 11)])).ranges(),
+            &[(0, 1), (5, 11)]
+        );
+        assert_eq!(
+            map.image(&RangeSet::new(vec![(3, 7)])).ranges(),
+            &[(5, 7), (13, 14)]
+        );
+    }
+}
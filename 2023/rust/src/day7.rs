@@ -8,128 +8,246 @@ use crate::parser;
 struct Hand {
     cards: String,
     bid: usize,
-    jokered: bool,
 }
 
-impl Hand {
-    fn hand_type_strength(&self) -> usize {
-        let card_groups = self.cards.chars().into_group_map_by(|it| *it);
-        let mut different_cards = card_groups.len();
-        let mut biggest_card_group = card_groups.values().map(|g| g.len()).max().unwrap();
+impl FromStr for Hand {
+    type Err = String;
 
-        if self.jokered {
-            biggest_card_group = card_groups
-                .iter()
-                .filter(|(c, _)| **c != 'J')
-                .map(|(_, v)| v)
-                .map(|g| g.len())
-                .max()
-                .unwrap_or(0);
-            let jokers = card_groups.get(&'J').map(|g| g.len()).unwrap_or(0);
-            if jokers > 0 {
-                biggest_card_group += jokers;
-                different_cards -= 1;
-                different_cards = different_cards.max(1);
-            }
-        }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (cards, bid) = s.split_once(' ').ok_or("Missing bid".to_string())?;
+        let bid = bid.trim().parse().map_err(|_| "Invalid bid".to_string())?;
 
-        match (different_cards, biggest_card_group) {
-            (1, _) => 7,
-            (2, 4) => 6,
-            (2, 3) => 5,
-            (3, 3) => 4,
-            (3, 2) => 3,
-            (4, _) => 2,
-            (5, _) => 1,
-            e => panic!("should be unreachable: {:?}, {}", e, self.cards),
-        }
+        Ok(Hand {
+            cards: cards.to_string(),
+            bid,
+        })
     }
+}
 
-    fn cards_strength(&self) -> Vec<usize> {
-        self.cards
-            .chars()
-            .map(|card| match card {
-                'A' => 14,
-                'K' => 13,
-                'Q' => 12,
-                'J' if self.jokered => 1,
-                'J' => 11,
-                'T' => 10,
-                c => c.to_string().parse().unwrap(),
-            })
-            .collect()
-    }
+// A card of a hand, where a wildcard shows the card it stands in for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Card {
+    face: char,
+    strength: usize,
 }
 
-impl FromStr for Hand {
-    type Err = String;
+type Predicate = Box<dyn Fn(&[Card]) -> bool>;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split(" ");
-        let cards = parts.next().unwrap().to_string();
-        let bid = parts.next().unwrap().parse().unwrap();
+// A named kind of hand, the predicate gets the cards from weakest to strongest
+struct Category {
+    name: String,
+    matches: Predicate,
+}
 
-        Ok(Hand {
-            cards,
-            bid,
-            jokered: false,
+impl Category {
+    fn new(name: &str, matches: impl Fn(&[Card]) -> bool + 'static) -> Self {
+        Category {
+            name: name.to_string(),
+            matches: Box::new(matches),
+        }
+    }
+
+    // Matches when the biggest groups of equal cards have at least these sizes
+    fn groups(name: &str, sizes: &[usize]) -> Self {
+        let sizes = sizes.to_vec();
+        Category::new(name, move |cards| {
+            let groups: Vec<usize> = cards
+                .iter()
+                .map(|card| card.strength)
+                .dedup_with_count()
+                .map(|(count, _)| count)
+                .sorted()
+                .rev()
+                .collect();
+            sizes.len() <= groups.len()
+                && sizes.iter().zip(&groups).all(|(size, group)| group >= size)
         })
     }
+
+    #[allow(dead_code)]
+    fn straight(name: &str) -> Self {
+        Category::new(name, |cards| {
+            cards
+                .windows(2)
+                .all(|pair| pair[1].strength == pair[0].strength + 1)
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HandError {
+    WrongSize(String),
+    UnknownCard(char),
+    NoCategory(String),
+}
+
+struct Rules {
+    // Weakest card first
+    card_order: Vec<char>,
+    wildcards: Vec<char>,
+    hand_size: usize,
+    // Strongest category first
+    categories: Vec<Category>,
 }
 
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.hand_type_strength()
-            .cmp(&other.hand_type_strength())
-            .then(self.cards_strength().cmp(&other.cards_strength()))
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Evaluation {
+    cards: String,
+    category: String,
+    category_strength: usize,
+    // Wildcards of the hand, in the order they appear
+    wildcards_used: String,
+    card_strengths: Vec<usize>,
+}
+
+impl Evaluation {
+    #[allow(dead_code)]
+    fn explain(&self) -> String {
+        match self.wildcards_used.chars().count() {
+            0 => format!("{} → {}", self.cards, self.category),
+            1 => format!(
+                "{} → {} via wildcard {}",
+                self.cards, self.category, self.wildcards_used
+            ),
+            _ => format!(
+                "{} → {} via wildcards {}",
+                self.cards, self.category, self.wildcards_used
+            ),
+        }
     }
 }
 
-impl PartialOrd for Hand {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+fn group_categories() -> Vec<Category> {
+    vec![
+        Category::groups("five of a kind", &[5]),
+        Category::groups("four of a kind", &[4]),
+        Category::groups("full house", &[3, 2]),
+        Category::groups("three of a kind", &[3]),
+        Category::groups("two pair", &[2, 2]),
+        Category::groups("one pair", &[2]),
+        Category::groups("high card", &[]),
+    ]
+}
+
+impl Rules {
+    fn camel_cards() -> Self {
+        Rules {
+            card_order: "23456789TJQKA".chars().collect(),
+            wildcards: vec![],
+            hand_size: 5,
+            categories: group_categories(),
+        }
+    }
+
+    // Jokers stand in for whatever card helps most, but are the weakest on their own
+    fn camel_cards_with_jokers() -> Self {
+        Rules {
+            card_order: "J23456789TQKA".chars().collect(),
+            wildcards: vec!['J'],
+            ..Rules::camel_cards()
+        }
+    }
+
+    fn card(&self, face: char) -> Result<Card, HandError> {
+        self.card_order
+            .iter()
+            .position(|&c| c == face)
+            .map(|strength| Card { face, strength })
+            .ok_or(HandError::UnknownCard(face))
+    }
+
+    // Index of the strongest category matching the hand
+    fn best_category(&self, cards: &[Card]) -> Option<usize> {
+        let sorted: Vec<Card> = cards
+            .iter()
+            .copied()
+            .sorted_by_key(|card| card.strength)
+            .collect();
+        self.categories
+            .iter()
+            .position(|category| (category.matches)(&sorted))
+    }
+
+    fn evaluate(&self, cards: &str) -> Result<Evaluation, HandError> {
+        if cards.chars().count() != self.hand_size {
+            return Err(HandError::WrongSize(cards.to_string()));
+        }
+        let card_strengths: Vec<usize> = cards
+            .chars()
+            .map(|card| self.card(card).map(|card| card.strength))
+            .try_collect()?;
+
+        let (wilds, fixed): (Vec<char>, Vec<char>) = cards
+            .chars()
+            .partition(|card| self.wildcards.contains(card));
+        let fixed: Vec<Card> = fixed
+            .into_iter()
+            .map(|card| self.card(card))
+            .try_collect()?;
+        let substitutes: Vec<Card> = self
+            .card_order
+            .iter()
+            .filter(|card| !self.wildcards.contains(card))
+            .map(|&card| self.card(card))
+            .try_collect()?;
+
+        // The order of the stand-ins does not matter, so multisets of them are enough
+        let category = substitutes
+            .into_iter()
+            .combinations_with_replacement(wilds.len())
+            .filter_map(|stand_ins| {
+                let resolved: Vec<Card> = fixed.iter().copied().chain(stand_ins).collect();
+                self.best_category(&resolved)
+            })
+            .min()
+            .ok_or(HandError::NoCategory(cards.to_string()))?;
+
+        Ok(Evaluation {
+            cards: cards.to_string(),
+            category: self.categories[category].name.clone(),
+            category_strength: self.categories.len() - category,
+            wildcards_used: wilds.iter().collect(),
+            card_strengths,
+        })
+    }
+
+    // Weakest hand first, hands that tie keep their input order
+    fn rank<'a>(&self, hands: &'a [Hand]) -> Result<Vec<(&'a Hand, Evaluation)>, HandError> {
+        let mut ranked: Vec<(&Hand, Evaluation)> = hands
+            .iter()
+            .map(|hand| Ok((hand, self.evaluate(&hand.cards)?)))
+            .collect::<Result<_, _>>()?;
+        ranked.sort_by(|(_, a), (_, b)| {
+            (a.category_strength, &a.card_strengths).cmp(&(b.category_strength, &b.card_strengths))
+        });
+        Ok(ranked)
     }
 }
 
 pub fn part1() -> usize {
     let hands: Vec<Hand> = parser::read("data/day7.txt").unwrap();
-    total_winnings(&hands)
+    total_winnings(&hands, &Rules::camel_cards()).unwrap()
 }
 
 pub fn part2() -> usize {
     let hands: Vec<Hand> = parser::read("data/day7.txt").unwrap();
-    total_winnings_with_joker(&hands)
+    total_winnings(&hands, &Rules::camel_cards_with_jokers()).unwrap()
 }
 
-fn total_winnings(hands: &Vec<Hand>) -> usize {
-    hands
+fn total_winnings(hands: &[Hand], rules: &Rules) -> Result<usize, HandError> {
+    Ok(rules
+        .rank(hands)?
         .iter()
-        .sorted()
         .enumerate()
-        .map(|(i, hand)| (i + 1) * hand.bid)
-        .sum()
-}
-
-fn total_winnings_with_joker(hands: &Vec<Hand>) -> usize {
-    hands
-        .iter()
-        .map(|hand| Hand {
-            cards: hand.cards.clone(),
-            bid: hand.bid,
-            jokered: true,
-        })
-        .sorted()
-        .enumerate()
-        .map(|(i, hand)| (i + 1) * hand.bid)
-        .sum()
+        .map(|(i, (hand, _))| (i + 1) * hand.bid)
+        .sum())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn sample_input_part_1() {
+    fn sample_hands() -> Vec<Hand> {
         let lines = vec![
             "32T3K 765",
             "T55J5 684",
@@ -137,26 +255,113 @@ mod tests {
             "KTJJT 220",
             "QQQJA 483",
         ];
-        let hands: Vec<Hand> = lines.into_iter().map(|s| s.parse().unwrap()).collect();
+        lines.into_iter().map(|s| s.parse().unwrap()).collect()
+    }
 
-        let result = total_winnings(&hands);
+    #[test]
+    fn explanations() {
+        let rules = Rules::camel_cards_with_jokers();
 
-        assert_eq!(result, 6440)
+        let explain = |cards: &str| rules.evaluate(cards).unwrap().explain();
+        assert_eq!(explain("JQQ22"), "JQQ22 → full house via wildcard J");
+        assert_eq!(explain("JJQQ2"), "JJQQ2 → four of a kind via wildcards JJ");
+        assert_eq!(
+            explain("JJJJJ"),
+            "JJJJJ → five of a kind via wildcards JJJJJ"
+        );
+        assert_eq!(explain("32T3K"), "32T3K → one pair");
+
+        assert_eq!(
+            rules.evaluate("32T3"),
+            Err(HandError::WrongSize("32T3".to_string()))
+        );
+        assert_eq!(rules.evaluate("32T3X"), Err(HandError::UnknownCard('X')));
     }
 
     #[test]
-    fn sample_input_part_2() {
-        let lines = vec![
-            "32T3K 765",
-            "T55J5 684",
-            "KK677 28",
-            "KTJJT 220",
-            "QQQJA 483",
-        ];
-        let hands: Vec<Hand> = lines.into_iter().map(|s| s.parse().unwrap()).collect();
+    fn pluggable_rules() {
+        // Two wildcards, six card hands and a straight above four of a kind
+        let mut categories = group_categories();
+        categories.insert(2, Category::straight("straight"));
+        let rules = Rules {
+            card_order: "123456789".chars().collect(),
+            wildcards: vec!['1', '9'],
+            hand_size: 6,
+            categories,
+        };
+
+        let evaluation = rules.evaluate("234516").unwrap();
+        assert_eq!(evaluation.explain(), "234516 → straight via wildcard 1");
+        let evaluation = rules.evaluate("912345").unwrap();
+        assert_eq!(evaluation.explain(), "912345 → straight via wildcards 91");
+        assert_eq!(rules.evaluate("222333").unwrap().category, "full house");
+
+        let no_pairs = Rules {
+            categories: vec![Category::new("all different", |cards| {
+                cards.iter().map(|card| card.strength).all_unique()
+            })],
+            ..Rules::camel_cards()
+        };
+        assert_eq!(
+            no_pairs.evaluate("22345"),
+            Err(HandError::NoCategory("22345".to_string()))
+        );
+    }
+
+    #[test]
+    fn categories_reading_the_cards() {
+        // Lower case cards are one suit and upper case ones the other, the joker can be either
+        let mut categories = group_categories();
+        categories.insert(
+            1,
+            Category::new("flush", |cards| {
+                cards
+                    .iter()
+                    .map(|card| card.face.is_lowercase())
+                    .all_equal()
+            }),
+        );
+        let rules = Rules {
+            card_order: "*abcdeABCDE".chars().collect(),
+            wildcards: vec!['*'],
+            hand_size: 4,
+            categories,
+        };
 
-        let result = total_winnings_with_joker(&hands);
+        assert_eq!(rules.evaluate("abec").unwrap().category, "flush");
+        assert_eq!(
+            rules.evaluate("ACE*").unwrap().explain(),
+            "ACE* → flush via wildcard *"
+        );
+        assert_eq!(rules.evaluate("aAbC").unwrap().category, "high card");
+        assert_eq!(rules.evaluate("aaa*").unwrap().category, "flush");
+        assert_eq!(rules.evaluate("aaA*").unwrap().category, "three of a kind");
+    }
+
+    #[test]
+    fn ties_keep_input_order() {
+        let hands: Vec<Hand> = ["KK677 1", "AAAAA 2", "KK677 3", "22345 4"]
+            .into_iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+
+        let ranked = Rules::camel_cards().rank(&hands).unwrap();
+
+        let bids: Vec<usize> = ranked.iter().map(|(hand, _)| hand.bid).collect();
+        assert_eq!(bids, vec![4, 1, 3, 2]);
+    }
+
+    #[test]
+    fn sample_input_part_1() {
+        let result = total_winnings(&sample_hands(), &Rules::camel_cards());
+
+        assert_eq!(result, Ok(6440))
+    }
+
+    #[test]
+    fn sample_input_part_2() {
+        let result = total_winnings(&sample_hands(), &Rules::camel_cards_with_jokers());
 
-        assert_eq!(result, 5905)
+        assert_eq!(result, Ok(5905))
     }
 }
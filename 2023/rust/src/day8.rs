@@ -1,20 +1,23 @@
 use std::collections::HashMap;
 
+use itertools::Itertools;
+
+use crate::congruence::crt;
 use crate::parser;
 
 pub fn part1() -> usize {
     let lines: Vec<String> = parser::read("data/day8.txt").unwrap();
     let (moves, network) = parse(&lines);
-    step_count(&"AAA".to_string(), &moves, &network)
+    step_count("AAA", &moves, &network)
 }
 
 pub fn part2() -> usize {
     let lines: Vec<String> = parser::read("data/day8.txt").unwrap();
     let (moves, network) = parse(&lines);
-    step_count_multiple(&moves, &network)
+    step_count_multiple(&moves, &network).unwrap()
 }
 
-fn parse(lines: &Vec<String>) -> (Vec<char>, HashMap<String, (String, String)>) {
+fn parse(lines: &[String]) -> (Vec<char>, HashMap<String, (String, String)>) {
     let moves = lines[0].chars().collect();
     let mut network = HashMap::new();
 
@@ -31,12 +34,8 @@ fn parse(lines: &Vec<String>) -> (Vec<char>, HashMap<String, (String, String)>)
     (moves, network)
 }
 
-fn step_count(
-    initial: &String,
-    moves: &Vec<char>,
-    network: &HashMap<String, (String, String)>,
-) -> usize {
-    let mut node = initial.clone();
+fn step_count(initial: &str, moves: &[char], network: &HashMap<String, (String, String)>) -> usize {
+    let mut node = initial.to_string();
     let mut i = 0;
     let mut steps = 0;
 
@@ -50,42 +49,134 @@ fn step_count(
         };
 
         i += 1;
-        i = i % moves.len();
+        i %= moves.len();
         steps += 1;
     }
 
     steps
 }
 
-fn step_count_multiple(moves: &Vec<char>, network: &HashMap<String, (String, String)>) -> usize {
-    let nodes: Vec<String> = network
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum GhostError {
+    NoGhosts,
+    UnknownNode(String),
+    NeverOnZ(String),
+    NeverSynchronised,
+}
+
+// A ghost is on a Z node at the steps in prefix_hits, then at every cycle hit plus any
+// multiple of the cycle length
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GhostCycle {
+    cycle_start: usize,
+    cycle_length: usize,
+    prefix_hits: Vec<usize>,
+    cycle_hits: Vec<usize>,
+}
+
+impl GhostCycle {
+    fn on_z_at(&self, step: usize) -> bool {
+        if step < self.cycle_start {
+            return self.prefix_hits.contains(&step);
+        }
+        let in_cycle = self.cycle_start + (step - self.cycle_start) % self.cycle_length;
+        self.cycle_hits.contains(&in_cycle)
+    }
+}
+
+// Walks until a (node, instruction index) state shows up again
+fn analyse_ghost(
+    start: &str,
+    moves: &[char],
+    network: &HashMap<String, (String, String)>,
+) -> Result<GhostCycle, GhostError> {
+    let mut seen: HashMap<(&str, usize), usize> = HashMap::new();
+    let mut hits = vec![];
+    let mut node = start;
+    let mut step = 0;
+
+    loop {
+        let i = step % moves.len();
+        if let Some(&cycle_start) = seen.get(&(node, i)) {
+            let (prefix_hits, cycle_hits) = hits.into_iter().partition(|&hit| hit < cycle_start);
+            return Ok(GhostCycle {
+                cycle_start,
+                cycle_length: step - cycle_start,
+                prefix_hits,
+                cycle_hits,
+            });
+        }
+        seen.insert((node, i), step);
+
+        if node.ends_with('Z') {
+            hits.push(step);
+        }
+        let (left, right) = network
+            .get(node)
+            .ok_or(GhostError::UnknownNode(node.to_string()))?;
+        node = if moves[i] == 'L' { left } else { right };
+        step += 1;
+    }
+}
+
+fn step_count_multiple(
+    moves: &[char],
+    network: &HashMap<String, (String, String)>,
+) -> Result<usize, GhostError> {
+    let starts: Vec<&String> = network
         .keys()
-        .filter(|node| node.ends_with("A"))
-        .cloned()
+        .filter(|node| node.ends_with('A'))
+        .sorted()
         .collect();
 
-    let steps_to_z: Vec<usize> = nodes
-        .iter()
-        .map(|node| step_count(node, moves, network))
-        .collect();
+    let ghosts: Vec<GhostCycle> = starts
+        .into_iter()
+        .map(|start| {
+            let ghost = analyse_ghost(start, moves, network)?;
+            if ghost.prefix_hits.is_empty() && ghost.cycle_hits.is_empty() {
+                return Err(GhostError::NeverOnZ(start.clone()));
+            }
+            Ok(ghost)
+        })
+        .try_collect()?;
 
-    lcm(&steps_to_z)
+    first_synchronised_step(&ghosts)
 }
 
-fn lcm(nums: &[usize]) -> usize {
-    if nums.len() == 1 {
-        return nums[0];
+fn first_synchronised_step(ghosts: &[GhostCycle]) -> Result<usize, GhostError> {
+    let latest = ghosts
+        .iter()
+        .max_by_key(|ghost| ghost.cycle_start)
+        .ok_or(GhostError::NoGhosts)?;
+
+    // Before every ghost is in its cycle, a common step has to be a prefix hit of the last
+    // one to get there
+    if let Some(&step) = latest
+        .prefix_hits
+        .iter()
+        .find(|&&step| ghosts.iter().all(|ghost| ghost.on_z_at(step)))
+    {
+        return Ok(step);
     }
-    let a = nums[0];
-    let b = lcm(&nums[1..]);
-    a * b / gcd_of_two_numbers(a, b)
-}
 
-fn gcd_of_two_numbers(a: usize, b: usize) -> usize {
-    if b == 0 {
-        return a;
+    // Afterwards each ghost allows one congruence per cycle hit, any choice of them will do
+    let mut congruences = vec![(0, 1)];
+    for ghost in ghosts {
+        let length = ghost.cycle_length as i128;
+        congruences = congruences
+            .iter()
+            .cartesian_product(&ghost.cycle_hits)
+            .filter_map(|(&congruence, &hit)| crt(congruence, (hit as i128 % length, length)))
+            .unique()
+            .collect();
     }
-    gcd_of_two_numbers(b, a % b)
+
+    let settled = latest.cycle_start as i128;
+    congruences
+        .into_iter()
+        .map(|(residue, modulus)| (settled + (residue - settled).rem_euclid(modulus)) as usize)
+        .min()
+        .ok_or(GhostError::NeverSynchronised)
 }
 
 #[cfg(test)]
@@ -108,7 +199,7 @@ mod tests {
         let lines: Vec<String> = lines.into_iter().map(|s| s.parse().unwrap()).collect();
         let (moves, network) = parse(&lines);
 
-        let result = step_count(&"AAA".to_string(), &moves, &network);
+        let result = step_count("AAA", &moves, &network);
 
         assert_eq!(result, 2);
     }
@@ -125,7 +216,7 @@ mod tests {
         let lines: Vec<String> = lines.into_iter().map(|s| s.parse().unwrap()).collect();
         let (moves, network) = parse(&lines);
 
-        let result = step_count(&"AAA".to_string(), &moves, &network);
+        let result = step_count("AAA", &moves, &network);
 
         assert_eq!(result, 6);
     }
@@ -149,6 +240,75 @@ mod tests {
 
         let result = step_count_multiple(&moves, &network);
 
-        assert_eq!(result, 6);
+        assert_eq!(result, Ok(6));
+    }
+
+    fn single_move_network(lines: &[&str]) -> (Vec<char>, HashMap<String, (String, String)>) {
+        let lines: Vec<String> = ["L", ""]
+            .iter()
+            .chain(lines)
+            .map(|s| s.to_string())
+            .collect();
+        parse(&lines)
+    }
+
+    #[test]
+    fn late_cycles_with_several_z_nodes() {
+        // 1A reaches its loop after 4 steps, 2A sees two Z nodes every 4 steps
+        let (moves, network) = single_move_network(&[
+            "1A = (1B, 1B)",
+            "1B = (1C, 1C)",
+            "1C = (1D, 1D)",
+            "1D = (1Z, 1Z)",
+            "1Z = (1E, 1E)",
+            "1E = (1F, 1F)",
+            "1F = (1G, 1G)",
+            "1G = (1H, 1H)",
+            "1H = (1Z, 1Z)",
+            "2A = (2B, 2B)",
+            "2B = (2PZ, 2PZ)",
+            "2PZ = (2QZ, 2QZ)",
+            "2QZ = (2C, 2C)",
+            "2C = (2B, 2B)",
+        ]);
+
+        assert_eq!(
+            analyse_ghost("2A", &moves, &network),
+            Ok(GhostCycle {
+                cycle_start: 1,
+                cycle_length: 4,
+                prefix_hits: vec![],
+                cycle_hits: vec![2, 3],
+            })
+        );
+        assert_eq!(step_count_multiple(&moves, &network), Ok(14));
+    }
+
+    #[test]
+    fn unsolvable_networks() {
+        let (moves, network) = single_move_network(&[
+            "3A = (3Z, 3Z)",
+            "3Z = (3B, 3B)",
+            "3B = (3Z, 3Z)",
+            "4A = (4B, 4B)",
+            "4B = (4Z, 4Z)",
+            "4Z = (4B, 4B)",
+        ]);
+        assert_eq!(
+            step_count_multiple(&moves, &network),
+            Err(GhostError::NeverSynchronised)
+        );
+
+        let (moves, network) = single_move_network(&["5A = (5B, 5B)", "5B = (5B, 5B)"]);
+        assert_eq!(
+            step_count_multiple(&moves, &network),
+            Err(GhostError::NeverOnZ("5A".to_string()))
+        );
+
+        let (moves, network) = single_move_network(&["6A = (6B, 6B)"]);
+        assert_eq!(
+            step_count_multiple(&moves, &network),
+            Err(GhostError::UnknownNode("6B".to_string()))
+        );
     }
 }